@@ -0,0 +1,173 @@
+use std::io::Result;
+
+use libc;
+
+use {Mmap, MmapMut};
+
+/// Values supported by [`Mmap::advise()`] and [`MmapMut::advise()`].
+///
+/// These advice values only affect the performance of the memory map, never its contents. See
+/// [`UncheckedAdvice`] for advice values which may discard the contents of the memory map.
+///
+/// See the [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page for the
+/// full semantics of each value.
+///
+/// [`Mmap::advise()`]: crate::Mmap::advise()
+/// [`MmapMut::advise()`]: crate::MmapMut::advise()
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /// **MADV_NORMAL**
+    ///
+    /// No special treatment. This is the default.
+    Normal = libc::MADV_NORMAL,
+
+    /// **MADV_RANDOM**
+    ///
+    /// Expect page references in random order. Read-ahead may be less useful than normally.
+    Random = libc::MADV_RANDOM,
+
+    /// **MADV_SEQUENTIAL**
+    ///
+    /// Expect page references in sequential order. Pages may be read ahead aggressively, and may
+    /// be freed soon after they are accessed.
+    Sequential = libc::MADV_SEQUENTIAL,
+
+    /// **MADV_WILLNEED**
+    ///
+    /// Expect access in the near future. It might be a good idea to read some pages ahead.
+    WillNeed = libc::MADV_WILLNEED,
+
+    /// **MADV_HUGEPAGE**
+    ///
+    /// Enable Transparent Huge Pages (THP) for pages in the range.
+    #[cfg(target_os = "linux")]
+    HugePage = libc::MADV_HUGEPAGE,
+
+    /// **MADV_NOHUGEPAGE**
+    ///
+    /// Ensure that pages in the range will not be backed by transparent huge pages.
+    #[cfg(target_os = "linux")]
+    NoHugePage = libc::MADV_NOHUGEPAGE,
+
+    /// **MADV_COLD** (since Linux 5.4)
+    ///
+    /// Deactivate the pages in the range, making them a more likely target for reclaim under
+    /// memory pressure. The contents of the pages are preserved.
+    #[cfg(target_os = "linux")]
+    Cold = libc::MADV_COLD,
+
+    /// **MADV_PAGEOUT** (since Linux 5.4)
+    ///
+    /// Reclaim the pages in the range, writing them back to their backing store or swap. The
+    /// contents of the pages are preserved, and are faulted back in on the next access.
+    #[cfg(target_os = "linux")]
+    PageOut = libc::MADV_PAGEOUT,
+}
+
+/// Values supported by [`Mmap::unchecked_advise()`] and [`MmapMut::unchecked_advise()`].
+///
+/// Unlike [`Advice`], these advice values may discard the contents of the memory map, and so can
+/// invalidate data the program is relying on. They are only accepted by the `unsafe` advise
+/// methods.
+///
+/// See the [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page for the
+/// full semantics of each value.
+///
+/// [`Mmap::unchecked_advise()`]: crate::Mmap::unchecked_advise()
+/// [`MmapMut::unchecked_advise()`]: crate::MmapMut::unchecked_advise()
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UncheckedAdvice {
+    /// **MADV_DONTNEED**
+    ///
+    /// Do not expect access in the near future. On Linux, subsequent accesses to private
+    /// mappings (including copy-on-write file maps) will see zero-filled pages or the original
+    /// file contents, discarding any changes made through the memory map.
+    DontNeed = libc::MADV_DONTNEED,
+
+    /// **MADV_FREE** (since Linux 4.5)
+    ///
+    /// The pages in the range of a private anonymous memory map may be freed lazily. Until they
+    /// are written to again, reading them may return either their previous contents or zeros.
+    #[cfg(target_os = "linux")]
+    Free = libc::MADV_FREE,
+
+    /// **MADV_REMOVE** (since Linux 2.6.16)
+    ///
+    /// Free the pages in the range and their backing store, punching a hole in the underlying
+    /// file or shared memory object. Subsequent accesses see zeros.
+    #[cfg(target_os = "linux")]
+    Remove = libc::MADV_REMOVE,
+}
+
+macro_rules! impl_advice {
+    ($map:ident) => {
+        impl $map {
+            /// Advise the OS about the expected access pattern of the memory map.
+            ///
+            /// Only supported on Unix.
+            ///
+            /// See [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page.
+            pub fn advise(&self, advice: Advice) -> Result<()> {
+                self.inner
+                    .madvise(advice as libc::c_int, 0, self.inner.len())
+            }
+
+            /// Advise the OS about the expected access pattern of a range of the memory map.
+            ///
+            /// The offset and length must be in the bounds of the memory map. The range is
+            /// extended downwards to the start of the page containing `offset`.
+            ///
+            /// Only supported on Unix.
+            ///
+            /// See [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page.
+            pub fn advise_range(&self, advice: Advice, offset: usize, len: usize) -> Result<()> {
+                self.inner.madvise(advice as libc::c_int, offset, len)
+            }
+
+            /// Advise the OS about the expected access pattern of the memory map, using advice
+            /// values which may discard its contents.
+            ///
+            /// Only supported on Unix.
+            ///
+            /// See [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page.
+            ///
+            /// # Safety
+            ///
+            /// Depending on the advice and the kind of memory map, the contents of the memory map
+            /// may be replaced by zeros or by the contents of the underlying file. The caller must
+            /// ensure that no data which is still needed is discarded, and that no references to
+            /// the discarded contents are relied upon.
+            pub unsafe fn unchecked_advise(&self, advice: UncheckedAdvice) -> Result<()> {
+                self.inner
+                    .madvise(advice as libc::c_int, 0, self.inner.len())
+            }
+
+            /// Advise the OS about the expected access pattern of a range of the memory map, using
+            /// advice values which may discard its contents.
+            ///
+            /// The offset and length must be in the bounds of the memory map. The range is
+            /// extended downwards to the start of the page containing `offset`.
+            ///
+            /// Only supported on Unix.
+            ///
+            /// See [`madvise()`](https://man7.org/linux/man-pages/man2/madvise.2.html) man page.
+            ///
+            /// # Safety
+            ///
+            /// See [`unchecked_advise()`](Self::unchecked_advise()).
+            pub unsafe fn unchecked_advise_range(
+                &self,
+                advice: UncheckedAdvice,
+                offset: usize,
+                len: usize,
+            ) -> Result<()> {
+                self.inner.madvise(advice as libc::c_int, offset, len)
+            }
+        }
+    };
+}
+
+impl_advice!(Mmap);
+impl_advice!(MmapMut);
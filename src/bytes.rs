@@ -0,0 +1,167 @@
+use std::ops::{Bound, Deref, RangeBounds};
+use std::sync::Arc;
+use std::{fmt, slice};
+
+use {Mmap, MmapInner};
+
+/// A cheaply cloneable, reference-counted slice of a read-only memory map.
+///
+/// Handles can be cloned and sliced without copying the mapped bytes, and without borrowing
+/// the memory map: the memory map stays alive until the last handle referring to it is dropped.
+/// This makes it possible to hand out independent slices of one memory map to other threads.
+///
+/// A `MmapBytes` is created by converting a [`Mmap`].
+///
+/// ## Example
+///
+/// ```
+/// use memmap::{MmapBytes, MmapOptions};
+/// use std::thread;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut mmap = MmapOptions::new().len(8).map_anon()?;
+/// mmap.copy_from_slice(b"abcdefgh");
+/// let bytes = MmapBytes::from(mmap.make_read_only()?);
+///
+/// let tail = bytes.slice(4..);
+/// let handle = thread::spawn(move || tail.to_vec());
+/// assert_eq!(b"efgh", &handle.join().unwrap()[..]);
+/// assert_eq!(b"abcd", &bytes[..4]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MmapBytes {
+    inner: Arc<MmapInner>,
+    offset: usize,
+    len: usize,
+}
+
+impl MmapBytes {
+    /// Returns the length of the slice in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a handle to a sub-slice of this slice.
+    ///
+    /// The sub-slice keeps the memory map alive, like any other handle.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the range is out of the bounds of the slice, or when its start is
+    /// greater than its end.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> MmapBytes {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len,
+        };
+        assert!(
+            start <= end,
+            "range start {} is greater than range end {}",
+            start,
+            end
+        );
+        assert!(
+            end <= self.len,
+            "range end {} is out of the bounds of the slice of {} bytes",
+            end,
+            self.len
+        );
+        MmapBytes {
+            inner: self.inner.clone(),
+            offset: self.offset + start,
+            len: end - start,
+        }
+    }
+}
+
+impl From<Mmap> for MmapBytes {
+    fn from(mmap: Mmap) -> MmapBytes {
+        MmapBytes {
+            len: mmap.inner.len(),
+            inner: Arc::new(mmap.inner),
+            offset: 0,
+        }
+    }
+}
+
+impl Deref for MmapBytes {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.inner.ptr().add(self.offset), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for MmapBytes {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.deref()
+    }
+}
+
+impl fmt::Debug for MmapBytes {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("MmapBytes")
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MmapBytes;
+    use MmapOptions;
+
+    fn bytes() -> MmapBytes {
+        let mut mmap = MmapOptions::new().len(16).map_anon().unwrap();
+        for (i, byte) in mmap.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        MmapBytes::from(mmap.make_read_only().unwrap())
+    }
+
+    #[test]
+    fn slice() {
+        let bytes = bytes();
+        assert_eq!(16, bytes.len());
+
+        let slice = bytes.slice(4..12);
+        assert_eq!(&[4, 5, 6, 7, 8, 9, 10, 11], &slice[..]);
+        let slice = slice.slice(2..=3);
+        assert_eq!(&[6, 7], &slice[..]);
+        assert!(slice.slice(2..).is_empty());
+        assert_eq!(&bytes[..], &bytes.slice(..)[..]);
+
+        // Slices keep the memory map alive.
+        drop(bytes);
+        assert_eq!(&[6, 7], &slice.clone()[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_out_of_bounds() {
+        bytes().slice(8..17);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_inverted() {
+        #[allow(clippy::reversed_empty_ranges)]
+        bytes().slice(8..4);
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{Error, ErrorKind, Result};
+
+use {Mmap, MmapMut};
+
+/// Returns the `N` bytes at `offset`, or `None` if they are out of bounds.
+fn read_bytes<const N: usize>(bytes: &[u8], offset: usize) -> Option<[u8; N]> {
+    let end = offset.checked_add(N)?;
+    let mut value = [0; N];
+    value.copy_from_slice(bytes.get(offset..end)?);
+    Some(value)
+}
+
+/// Writes `value` at `offset`, or returns an error if it is out of bounds.
+fn write_bytes<const N: usize>(bytes: &mut [u8], offset: usize, value: [u8; N]) -> Result<()> {
+    let len = bytes.len();
+    match offset
+        .checked_add(N)
+        .and_then(move |end| bytes.get_mut(offset..end))
+    {
+        Some(dst) => {
+            dst.copy_from_slice(&value);
+            Ok(())
+        }
+        None => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "write of {} bytes at offset {} is out of the bounds of the memory map of {} bytes",
+                N, offset, len
+            ),
+        )),
+    }
+}
+
+macro_rules! read_accessors {
+    ($($ty:ident: $read:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// Returns `None` if the value is out of the bounds of the memory map.
+            #[inline]
+            pub fn $read(&self, offset: usize) -> Option<$ty> {
+                read_bytes(self, offset).map($ty::from_ne_bytes)
+            }
+        )*
+    };
+    ($($ty:ident: $le:ident, $be:ident, $ne:ident;)*) => {
+        $(
+            #[doc = concat!("Reads a little-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned. Returns `None` if the value is out of the
+            /// bounds of the memory map.
+            #[inline]
+            pub fn $le(&self, offset: usize) -> Option<$ty> {
+                read_bytes(self, offset).map($ty::from_le_bytes)
+            }
+
+            #[doc = concat!("Reads a big-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned. Returns `None` if the value is out of the
+            /// bounds of the memory map.
+            #[inline]
+            pub fn $be(&self, offset: usize) -> Option<$ty> {
+                read_bytes(self, offset).map($ty::from_be_bytes)
+            }
+
+            #[doc = concat!("Reads a native-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned. Returns `None` if the value is out of the
+            /// bounds of the memory map.
+            #[inline]
+            pub fn $ne(&self, offset: usize) -> Option<$ty> {
+                read_bytes(self, offset).map($ty::from_ne_bytes)
+            }
+        )*
+    };
+}
+
+macro_rules! write_accessors {
+    ($($ty:ident: $write:ident;)*) => {
+        $(
+            #[doc = concat!("Writes a `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error if the value is out of the bounds of the memory map.
+            #[inline]
+            pub fn $write(&mut self, offset: usize, value: $ty) -> Result<()> {
+                write_bytes(self, offset, value.to_ne_bytes())
+            }
+        )*
+    };
+    ($($ty:ident: $le:ident, $be:ident, $ne:ident;)*) => {
+        $(
+            #[doc = concat!("Writes a little-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error if the value is out of the bounds of the memory map.
+            #[inline]
+            pub fn $le(&mut self, offset: usize, value: $ty) -> Result<()> {
+                write_bytes(self, offset, value.to_le_bytes())
+            }
+
+            #[doc = concat!("Writes a big-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error if the value is out of the bounds of the memory map.
+            #[inline]
+            pub fn $be(&mut self, offset: usize, value: $ty) -> Result<()> {
+                write_bytes(self, offset, value.to_be_bytes())
+            }
+
+            #[doc = concat!("Writes a native-endian `", stringify!($ty), "` at `offset`.")]
+            ///
+            /// The offset does not need to be aligned.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error if the value is out of the bounds of the memory map.
+            #[inline]
+            pub fn $ne(&mut self, offset: usize, value: $ty) -> Result<()> {
+                write_bytes(self, offset, value.to_ne_bytes())
+            }
+        )*
+    };
+}
+
+macro_rules! impl_read_accessors {
+    ($map:ident) => {
+        impl $map {
+            read_accessors! {
+                u8: read_u8;
+                i8: read_i8;
+            }
+
+            read_accessors! {
+                u16: read_u16_le, read_u16_be, read_u16_ne;
+                u32: read_u32_le, read_u32_be, read_u32_ne;
+                u64: read_u64_le, read_u64_be, read_u64_ne;
+                u128: read_u128_le, read_u128_be, read_u128_ne;
+                i16: read_i16_le, read_i16_be, read_i16_ne;
+                i32: read_i32_le, read_i32_be, read_i32_ne;
+                i64: read_i64_le, read_i64_be, read_i64_ne;
+                i128: read_i128_le, read_i128_be, read_i128_ne;
+                f32: read_f32_le, read_f32_be, read_f32_ne;
+                f64: read_f64_le, read_f64_be, read_f64_ne;
+            }
+        }
+    };
+}
+
+impl_read_accessors!(Mmap);
+impl_read_accessors!(MmapMut);
+
+impl MmapMut {
+    write_accessors! {
+        u8: write_u8;
+        i8: write_i8;
+    }
+
+    write_accessors! {
+        u16: write_u16_le, write_u16_be, write_u16_ne;
+        u32: write_u32_le, write_u32_be, write_u32_ne;
+        u64: write_u64_le, write_u64_be, write_u64_ne;
+        u128: write_u128_le, write_u128_be, write_u128_ne;
+        i16: write_i16_le, write_i16_be, write_i16_ne;
+        i32: write_i32_le, write_i32_be, write_i32_ne;
+        i64: write_i64_le, write_i64_be, write_i64_ne;
+        i128: write_i128_le, write_i128_be, write_i128_ne;
+        f32: write_f32_le, write_f32_be, write_f32_ne;
+        f64: write_f64_le, write_f64_be, write_f64_ne;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use MmapOptions;
+
+    #[test]
+    fn read_write() {
+        let mut mmap = MmapOptions::new().len(32).map_anon().unwrap();
+        mmap.write_u32_le(1, 0x01020304).unwrap();
+        assert_eq!(&[0, 4, 3, 2, 1, 0], &mmap[..6]);
+        assert_eq!(Some(0x01020304), mmap.read_u32_le(1));
+        assert_eq!(Some(0x04030201), mmap.read_u32_be(1));
+        assert_eq!(Some(0x01020304u32.to_le()), mmap.read_u32_ne(1));
+
+        mmap.write_i64_be(9, -2).unwrap();
+        assert_eq!(
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe],
+            &mmap[9..17]
+        );
+        assert_eq!(Some(-2), mmap.read_i64_be(9));
+
+        mmap.write_f64_ne(17, 1.5).unwrap();
+        assert_eq!(Some(1.5), mmap.read_f64_ne(17));
+
+        mmap.write_i8(31, -1).unwrap();
+        assert_eq!(Some(255), mmap.read_u8(31));
+
+        let mmap = mmap.make_read_only().unwrap();
+        assert_eq!(Some(0x01020304), mmap.read_u32_le(1));
+        assert_eq!(Some(-1), mmap.read_i8(31));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut mmap = MmapOptions::new().len(16).map_anon().unwrap();
+        assert_eq!(Some(0), mmap.read_u128_le(0));
+        assert_eq!(None, mmap.read_u128_le(1));
+        assert_eq!(None, mmap.read_u8(16));
+        assert_eq!(None, mmap.read_u16_be(usize::MAX));
+
+        assert!(mmap.write_u64_le(8, 1).is_ok());
+        assert!(mmap.write_u64_le(9, 1).is_err());
+        assert!(mmap.write_u16_be(usize::MAX, 1).is_err());
+        assert_eq!(Some(1), mmap.read_u64_le(8));
+    }
+}
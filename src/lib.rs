@@ -9,17 +9,77 @@ mod windows;
 #[cfg(windows)]
 use windows::MmapInner;
 
+#[cfg(unix)]
+extern crate libc;
 #[cfg(unix)]
 mod unix;
 #[cfg(unix)]
 use unix::MmapInner;
 
+#[cfg(unix)]
+mod advice;
+#[cfg(unix)]
+pub use advice::{Advice, UncheckedAdvice};
+
+#[cfg(target_os = "linux")]
+mod memfd;
+#[cfg(target_os = "linux")]
+pub use memfd::Memfd;
+
+#[cfg(target_os = "linux")]
+pub mod sync;
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sigbus;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use sigbus::catch_bus_errors;
+
+#[cfg(target_os = "linux")]
+mod ring;
+#[cfg(target_os = "linux")]
+pub use ring::RingBuffer;
+
+#[cfg(target_has_atomic = "64")]
+mod log;
+#[cfg(target_has_atomic = "64")]
+pub use log::{MmapLog, MmapLogReader};
+
+mod bytes;
+pub use bytes::MmapBytes;
+
+mod region;
+pub use region::MmapMutRegion;
+
+mod residency;
+
+mod endian;
+mod memlock;
+#[cfg(unix)]
+pub use memlock::{lock_all, unlock_all};
+mod page;
+pub use page::{align_down, align_up, allocation_granularity, huge_page_sizes, page_size};
+mod protect;
+mod reservation;
+pub use reservation::Reservation;
+mod view;
+pub use view::{AsBytes, Atomic, FromBytes, ViewError};
+
+#[cfg(all(unix, not(target_os = "android")))]
+mod shm;
+#[cfg(all(unix, not(target_os = "android")))]
+pub use shm::{SharedMemory, SharedMemoryOptions};
+
 use std::fmt;
 use std::fs::File;
 use std::io::{Error, ErrorKind, Result};
-use std::ops::{Deref, DerefMut};
-use std::slice;
-use std::usize;
+use std::ops::{Bound, Deref, DerefMut, RangeBounds};
+use std::{mem, ptr, result, slice};
 
 /// A memory map builder, providing advanced options and flags for specifying memory map behavior.
 ///
@@ -35,16 +95,31 @@ use std::usize;
 /// using file-backed maps. Solutions such as file permissions, locks or process-private (e.g.
 /// unlinked) files exist but are platform specific and limited.
 ///
+/// Cooperating processes can protect their memory maps with advisory locks, which are taken by
+/// the map constructors when configured with [`lock()`].
+///
+/// On Linux, [`map_sealed()`] safely maps memory files which have been sealed against
+/// modification.
+///
 /// [`map_anon()`]: MmapOptions::map_anon()
 /// [`map()`]: MmapOptions::map()
 /// [`map_mut()`]: MmapOptions::map_mut()
 /// [`map_exec()`]: MmapOptions::map_exec()
 /// [`map_copy()`]: MmapOptions::map_copy()
+/// [`map_sealed()`]: MmapOptions::map_sealed()
+/// [`lock()`]: MmapOptions::lock()
 #[derive(Clone, Debug, Default)]
 pub struct MmapOptions {
     offset: u64,
     len: Option<usize>,
     stack: bool,
+    populate: bool,
+    populate_write: bool,
+    locked: bool,
+    huge: Option<HugePageSize>,
+    private: bool,
+    lock: Option<(LockMode, LockWait)>,
+    inheritable: bool,
 }
 
 impl MmapOptions {
@@ -164,8 +239,306 @@ impl MmapOptions {
         self
     }
 
+    /// Configures the anonymous memory map to be backed by huge pages of the given size.
+    ///
+    /// Huge pages reduce TLB pressure for large, randomly accessed buffers. The length of the
+    /// memory map is rounded up to a multiple of the huge page size.
+    ///
+    /// This option corresponds to the `MAP_HUGETLB` flag (and the `MAP_HUGE_*` size selectors) on
+    /// Linux. Huge pages are allocated from the hugetlb pool, which must be reserved in advance
+    /// (see `/proc/sys/vm/nr_hugepages`); creating the memory map fails with an error if the pool
+    /// does not have enough free pages. On other platforms, creating an anonymous memory map with
+    /// this option fails.
+    ///
+    /// This option has no effect on file-backed memory maps.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::{HugePageSize, MmapOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mmap = MmapOptions::new()
+    ///     .huge_pages(HugePageSize::Size2MiB)
+    ///     .len(4096)
+    ///     .map_anon();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn huge_pages(&mut self, size: HugePageSize) -> &mut Self {
+        self.huge = Some(size);
+        self
+    }
+
+    /// Configures the anonymous memory map to be private to this process.
+    ///
+    /// The pages of a private anonymous memory map are ordinary process memory: they are not
+    /// backed by shared memory (and so are not accounted as `Shmem` in `/proc/meminfo`), and
+    /// child processes created with `fork` receive a copy-on-write copy of them rather than
+    /// sharing them. This is the most efficient choice for plain scratch buffers.
+    ///
+    /// This option corresponds to the `MAP_PRIVATE` flag on Unix. It has no effect on Windows.
+    ///
+    /// This option has no effect on file-backed memory maps; use [`map_copy()`] to create a
+    /// private file-backed memory map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut scratch = MmapOptions::new().private().len(4096).map_anon()?;
+    /// scratch[0] = 42;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`map_copy()`]: MmapOptions::map_copy()
+    pub fn private(&mut self) -> &mut Self {
+        self.private = true;
+        self
+    }
+
+    /// Configures the anonymous memory map to be shared with child processes.
+    ///
+    /// The pages of a shared anonymous memory map are backed by shared memory, and writes to them
+    /// are visible to child processes created with `fork`, and vice versa.
+    ///
+    /// This option corresponds to the `MAP_SHARED` flag on Unix, and is the default.
+    ///
+    /// This option has no effect on file-backed memory maps.
+    pub fn shared(&mut self) -> &mut Self {
+        self.private = false;
+        self
+    }
+
+    /// Configures the memory file created by [`map_memfd()`] to be inherited across `exec`.
+    ///
+    /// By default, the memory file is created with the `MFD_CLOEXEC` flag, so that it is closed
+    /// when the process executes a new program. This option creates it without the flag, so that
+    /// its file descriptor can be passed to a program started with `exec`.
+    ///
+    /// This option has no effect on other memory maps.
+    ///
+    /// [`map_memfd()`]: MmapOptions::map_memfd()
+    pub fn inheritable(&mut self) -> &mut Self {
+        self.inheritable = true;
+        self
+    }
+
+    /// Populates (prefaults) the page tables of the memory map when it is created.
+    ///
+    /// For file-backed memory maps this causes read-ahead on the file, so that later accesses to
+    /// the memory map do not block on page faults.
+    ///
+    /// This option corresponds to the `MAP_POPULATE` flag on Linux. Since the kernel treats the
+    /// flag as a best-effort hint, the memory map is also populated with
+    /// `madvise(MADV_POPULATE_READ)`, and a failure to populate it (for instance, because a
+    /// file-backed map extends past the end of the file) causes the map constructor to return an
+    /// error. On kernels older than Linux 5.14, which do not support `MADV_POPULATE_READ`, the
+    /// memory map is only populated with `MAP_POPULATE`. This option has no effect on other
+    /// platforms. Use [`populate_write()`] to prefault writeable memory maps for writing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let file = File::open("README.md")?;
+    ///
+    /// let mmap = unsafe {
+    ///     MmapOptions::new().populate().map(&file)?
+    /// };
+    ///
+    /// assert_eq!(&b"# memmap"[..], &mmap[..8]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`populate_write()`]: MmapOptions::populate_write()
+    pub fn populate(&mut self) -> &mut Self {
+        self.populate = true;
+        self
+    }
+
+    /// Populates the page tables of the memory map for writing when it is created.
+    ///
+    /// Pages are prefaulted writable, so that later writes to the memory map do not incur
+    /// copy-on-write or dirty-tracking faults. A failure to populate the memory map (for
+    /// instance, because a file-backed map extends past the end of the file, or because the
+    /// kernel does not support the operation) causes the map constructor to return an error.
+    ///
+    /// This option corresponds to `madvise(MADV_POPULATE_WRITE)` on Linux 5.14 and newer. On
+    /// other platforms, creating a writeable memory map with this option fails.
+    ///
+    /// This option only applies to writeable memory maps created with [`map_mut()`],
+    /// [`map_copy()`] and [`map_anon()`]; it has no effect on read-only and executable maps.
+    ///
+    /// [`populate()`]: MmapOptions::populate()
+    /// [`map_mut()`]: MmapOptions::map_mut()
+    /// [`map_copy()`]: MmapOptions::map_copy()
+    /// [`map_anon()`]: MmapOptions::map_anon()
+    pub fn populate_write(&mut self) -> &mut Self {
+        self.populate_write = true;
+        self
+    }
+
+    /// Locks the memory map into RAM when it is created, so that accessing it never page faults.
+    ///
+    /// On Linux, the memory map is created with the `MAP_LOCKED` flag. Since the kernel does not
+    /// report failures to lock the pages of a `MAP_LOCKED` memory map, the map constructors also
+    /// lock the memory map with `mlock`, and return an error if it fails, for instance because
+    /// the `RLIMIT_MEMLOCK` limit is exceeded. The memory map stays locked until it is dropped,
+    /// or unlocked with [`Mmap::unlock()`].
+    ///
+    /// This is unrelated to the advisory file lock configured with [`lock()`]. Creating a memory
+    /// map with this option fails on Windows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # #[cfg(not(unix))]
+    /// # fn main() {}
+    /// #
+    /// # #[cfg(unix)]
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut mmap = MmapOptions::new().len(4096).locked().map_anon()?;
+    /// mmap[0] = 42;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`lock()`]: MmapOptions::lock()
+    pub fn locked(&mut self) -> &mut Self {
+        self.locked = true;
+        self
+    }
+
+    /// Configures file-backed memory maps to take an advisory lock on the file.
+    ///
+    /// The file-backed map constructors take the lock before mapping the file, and the memory map
+    /// keeps it held until it is dropped. A shared lock may be held by any number of memory maps
+    /// at once, while an exclusive lock excludes all other locks. Advisory locks only protect
+    /// against processes which take them as well.
+    ///
+    /// On Unix, the lock is an `flock` lock; on Windows, it is a `LockFileEx` lock over the whole
+    /// file. Locks belong to the open file (description) which they are taken through, and a
+    /// duplicate of the file handle shares the open file with the caller's handle: a lock taken
+    /// through it would be the caller's lock, released when the caller unlocks the file, and
+    /// would not exclude other memory maps of the same handle. Instead, each memory map reopens
+    /// the file to get an open file of its own, which it takes the lock through and retains:
+    /// through `/proc/self/fd` on Linux and Android, through the file's path on macOS and iOS,
+    /// and with `ReOpenFile` on Windows.
+    ///
+    /// Reopening the file can fail even though the file is open, in which case the file-backed
+    /// map constructors return an error: when the file's permissions have changed so that it can
+    /// no longer be opened for reading, when `/proc` is not mounted (on Linux and Android), when
+    /// the file has been unlinked or its path now refers to another file (on macOS and iOS), or
+    /// when another handle to the file does not share read access (on Windows). On other
+    /// platforms, the file can not be reopened, and the file-backed map constructors always
+    /// return an error when a lock is configured. Note that Windows enforces locks on reads and
+    /// writes through other file handles.
+    ///
+    /// This option has no effect on anonymous memory maps.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate memmap;
+    /// # extern crate tempdir;
+    /// #
+    /// use std::fs::OpenOptions;
+    ///
+    /// use memmap::{LockMode, LockWait, MmapOptions};
+    /// #
+    /// # #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos",
+    /// #               target_os = "ios", windows)))]
+    /// # fn main() {}
+    /// #
+    /// # #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos",
+    /// #           target_os = "ios", windows))]
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tempdir = tempdir::TempDir::new("mmap")?;
+    /// # let path = tempdir.path().join("lock");
+    /// let file = OpenOptions::new().read(true).write(true).create(true).open(&path)?;
+    /// file.set_len(4096)?;
+    ///
+    /// let mmap = unsafe {
+    ///     MmapOptions::new()
+    ///         .lock(LockMode::Exclusive, LockWait::Blocking)
+    ///         .map_mut(&file)?
+    /// };
+    ///
+    /// // Other cooperating processes can not lock the file until the memory map is dropped.
+    /// let other = std::fs::File::open(&path)?;
+    /// assert!(unsafe {
+    ///     MmapOptions::new()
+    ///         .lock(LockMode::Shared, LockWait::NonBlocking)
+    ///         .map(&other)
+    /// }
+    /// .is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lock(&mut self, mode: LockMode, wait: LockWait) -> &mut Self {
+        self.lock = Some((mode, wait));
+        self
+    }
+
+    /// Maps a file with `map`, holding the configured advisory lock, if any, and recording the
+    /// state of the file for [`Mmap::check_backing()`].
+    fn map_file<F>(&self, file: &File, map: F) -> Result<MmapInner>
+    where
+        F: FnOnce(usize) -> Result<MmapInner>,
+    {
+        let lock = match self.lock {
+            Some((mode, wait)) => Some(MmapInner::lock(
+                file,
+                mode == LockMode::Exclusive,
+                wait == LockWait::Blocking,
+            )?),
+            None => None,
+        };
+        // The length of the file is read under the lock.
+        let mut inner = self.mlock(map(self.get_len(file)?)?)?;
+        if let Some(lock) = lock {
+            inner.set_lock(lock);
+        }
+        inner.set_backing(file)?;
+        Ok(inner)
+    }
+
+    /// Locks a memory map into RAM if configured with `locked()`.
+    fn mlock(&self, inner: MmapInner) -> Result<MmapInner> {
+        if self.locked {
+            inner.mlock(0, inner.len(), false)?;
+        }
+        Ok(inner)
+    }
+
+    /// Prefaults a memory map if configured with `populate()`, or for writing if it is `writeable`
+    /// and configured with `populate_write()`.
+    fn prefault(&self, inner: MmapInner, writeable: bool) -> Result<MmapInner> {
+        if writeable && self.populate_write {
+            inner.populate(true)?;
+        } else if self.populate {
+            inner.populate(false)?;
+        }
+        Ok(inner)
+    }
+
     /// Creates a read-only memory map backed by a file.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
@@ -193,22 +566,39 @@ impl MmapOptions {
     /// # }
     /// ```
     pub unsafe fn map(&self, file: &File) -> Result<Mmap> {
-        MmapInner::map(self.get_len(file)?, file, self.offset).map(|inner| Mmap { inner: inner })
+        self.map_file(file, |len| {
+            MmapInner::map(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, false))
+        .map(|inner| Mmap { inner })
     }
 
     /// Creates a readable and executable memory map backed by a file.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
     /// variety of reasons, such as when the file is not open with read permissions.
     pub unsafe fn map_exec(&self, file: &File) -> Result<Mmap> {
-        MmapInner::map_exec(self.get_len(file)?, file, self.offset)
-            .map(|inner| Mmap { inner: inner })
+        self.map_file(file, |len| {
+            MmapInner::map_exec(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, false))
+        .map(|inner| Mmap { inner })
     }
 
     /// Creates a writeable memory map backed by a file.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
@@ -241,8 +631,11 @@ impl MmapOptions {
     /// # }
     /// ```
     pub unsafe fn map_mut(&self, file: &File) -> Result<MmapMut> {
-        MmapInner::map_mut(self.get_len(file)?, file, self.offset)
-            .map(|inner| MmapMut { inner: inner })
+        self.map_file(file, |len| {
+            MmapInner::map_mut(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, true))
+        .map(|inner| MmapMut { inner })
     }
 
     /// Creates a copy-on-write memory map backed by a file.
@@ -250,6 +643,11 @@ impl MmapOptions {
     /// Data written to the memory map will not be visible by other processes,
     /// and will not be carried through to the underlying file.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
@@ -270,8 +668,11 @@ impl MmapOptions {
     /// # }
     /// ```
     pub unsafe fn map_copy(&self, file: &File) -> Result<MmapMut> {
-        MmapInner::map_copy(self.get_len(file)?, file, self.offset)
-            .map(|inner| MmapMut { inner: inner })
+        self.map_file(file, |len| {
+            MmapInner::map_copy(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, true))
+        .map(|inner| MmapMut { inner })
     }
 
     /// Creates an anonymous memory map.
@@ -283,10 +684,239 @@ impl MmapOptions {
     ///
     /// This method returns an error when the underlying system call fails.
     pub fn map_anon(&self) -> Result<MmapMut> {
-        MmapInner::map_anon(self.len.unwrap_or(0), self.stack).map(|inner| MmapMut { inner: inner })
+        MmapInner::map_anon(
+            self.len.unwrap_or(0),
+            self.stack,
+            self.populate,
+            self.locked,
+            self.huge,
+            self.private,
+        )
+        .and_then(|inner| self.mlock(inner))
+        .and_then(|inner| self.prefault(inner, true))
+        .map(|inner| MmapMut { inner })
+    }
+
+    /// Creates a raw memory map backed by a file.
+    ///
+    /// The memory map is readable and writeable, and shared with other processes mapping the
+    /// file. Unlike the other file-backed constructors, this constructor is safe: a [`MmapRaw`]
+    /// never hands out references to the mapped bytes, so it is sound for the file to be
+    /// modified while the memory map is alive. Accessing the memory map after the file has been
+    /// truncated may still raise `SIGBUS` on Unix.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system call fails, which can happen for a
+    /// variety of reasons, such as when the file is not open with read and write permissions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate memmap;
+    /// # extern crate tempdir;
+    /// #
+    /// use std::fs::OpenOptions;
+    ///
+    /// use memmap::MmapOptions;
+    /// #
+    /// # fn main() -> std::io::Result<()> {
+    /// # let tempdir = tempdir::TempDir::new("mmap")?;
+    /// # let path = tempdir.path().join("map_raw");
+    /// let file = OpenOptions::new().read(true).write(true).create(true).open(&path)?;
+    /// file.set_len(8)?;
+    ///
+    /// let mmap = MmapOptions::new().map_raw(&file)?;
+    /// mmap.write_volatile(0, 42u64)?;
+    /// assert_eq!(42u64, mmap.read_volatile(0)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_raw(&self, file: &File) -> Result<MmapRaw> {
+        self.map_file(file, |len| {
+            MmapInner::map_mut(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, true))
+        .map(|inner| MmapRaw {
+            inner,
+            writeable: true,
+        })
+    }
+
+    /// Creates a raw anonymous memory map.
+    ///
+    /// The memory map is configured like [`map_anon()`], but is returned as a [`MmapRaw`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system call fails.
+    ///
+    /// [`map_anon()`]: MmapOptions::map_anon()
+    pub fn map_raw_anon(&self) -> Result<MmapRaw> {
+        self.map_anon().map(MmapRaw::from)
+    }
+
+    /// Creates a read-only memory map backed by a sealed memory file.
+    ///
+    /// Unlike [`map()`], this constructor is safe: before mapping the file, it verifies that the
+    /// file is sealed with `F_SEAL_WRITE`, `F_SEAL_SHRINK` and `F_SEAL_GROW`, which guarantees
+    /// that the mapped contents can not be changed by this or any other process, and that the
+    /// memory map does not extend past the end of the file. Sealed files are typically created
+    /// with [`Memfd::seal()`], or received from a peer process. The file is mapped private and
+    /// read-only, since kernels before 6.7 refuse shared mappings of a file sealed with
+    /// `F_SEAL_WRITE` through a descriptor which is open for writing.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the file is not sealed, when the configured offset and
+    /// length extend past the end of the file, or when the underlying system call fails.
+    ///
+    /// [`map()`]: MmapOptions::map()
+    #[cfg(target_os = "linux")]
+    pub fn map_sealed(&self, file: &File) -> Result<Mmap> {
+        unix::check_sealed(file)?;
+        let file_len = file.metadata()?.len();
+        let end = match self.len {
+            Some(len) => self.offset.checked_add(len as u64),
+            None => Some(file_len),
+        };
+        if self.offset > file_len || end.map_or(true, |end| end > file_len) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "memory map extends past the end of the sealed file",
+            ));
+        }
+        self.map_file(file, |len| {
+            MmapInner::map_sealed(len, file, self.offset, self.populate, self.locked)
+        })
+        .and_then(|inner| self.prefault(inner, false))
+        .map(|inner| Mmap { inner })
+    }
+
+    /// Creates a writeable memory map backed by a new anonymous memory file.
+    ///
+    /// The memory file is created with `memfd_create`, using `name` as its name for debugging
+    /// purposes (it appears as `/memfd:name` in `/proc/self/maps`). Its memory can be mapped by
+    /// other processes through the file descriptor available from the returned [`Memfd`].
+    ///
+    /// If [`huge_pages()`] is configured, the memory file is backed by huge pages
+    /// (`MFD_HUGETLB`), and its length is rounded up to a multiple of the huge page size.
+    ///
+    /// Note: the memory map length must be configured to be greater than 0 before creating a
+    /// memfd memory map using `MmapOptions::len()`. The offset is ignored.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system calls fail, or when `name`
+    /// contains a nul byte.
+    ///
+    /// [`huge_pages()`]: MmapOptions::huge_pages()
+    #[cfg(target_os = "linux")]
+    pub fn map_memfd(&self, name: &str) -> Result<Memfd> {
+        let (inner, file) = MmapInner::map_memfd(
+            name,
+            self.len.unwrap_or(0),
+            self.populate,
+            self.locked,
+            self.huge,
+            !self.inheritable,
+        )?;
+        let inner = self.prefault(self.mlock(inner)?, true)?;
+        Ok(Memfd::new(file, MmapMut { inner }))
     }
 }
 
+/// Whether a memory map may be moved to a new address when it is resized.
+///
+/// See [`MmapMut::resize()`].
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemapPolicy {
+    /// The memory map may be moved to a new address if it can not be grown in place.
+    ///
+    /// This corresponds to the `MREMAP_MAYMOVE` flag.
+    MayMove,
+    /// The memory map must be resized in place; resizing fails if the address space following
+    /// the memory map is not free.
+    InPlace,
+}
+
+/// The size of the huge pages backing an anonymous memory map.
+///
+/// See [`MmapOptions::huge_pages()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HugePageSize {
+    /// The system's default huge page size.
+    Default,
+    /// 2 MiB huge pages.
+    Size2MiB,
+    /// 1 GiB huge pages.
+    Size1GiB,
+}
+
+/// The mode of an advisory file lock.
+///
+/// See [`MmapOptions::lock()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockMode {
+    /// A shared lock, which may be held by any number of memory maps at once.
+    Shared,
+    /// An exclusive lock, which excludes all other locks.
+    Exclusive,
+}
+
+/// Whether taking an advisory file lock waits for conflicting locks to be released.
+///
+/// See [`MmapOptions::lock()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockWait {
+    /// Block until the lock can be taken.
+    Blocking,
+    /// Fail with an error if the lock is held by someone else.
+    NonBlocking,
+}
+
+/// The state of the file backing a memory map, compared to when the memory map was created.
+///
+/// See [`Mmap::check_backing()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackingState {
+    /// The file has not changed.
+    Unchanged,
+    /// The file has grown.
+    Grown,
+    /// The file has shrunk. Accessing the memory map past the new end of the file raises
+    /// `SIGBUS` on Unix.
+    Truncated,
+    /// The path the file was mapped through no longer refers to the file: the file has been
+    /// removed, or replaced by another file. The memory map still refers to the original file.
+    Replaced,
+    /// The file has been modified, without changing its length.
+    Modified,
+}
+
+/// The access permitted to the pages of a memory map.
+///
+/// See [`MmapMut::protect_range()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protection {
+    /// The pages can not be accessed (`PROT_NONE`). Any access raises `SIGSEGV` on Unix, or an
+    /// access violation on Windows.
+    None,
+    /// The pages can be read.
+    Read,
+    /// The pages can be read and written.
+    ReadWrite,
+    /// The pages can be read and executed.
+    ReadExec,
+    /// The pages can be read, written and executed.
+    ReadWriteExec,
+}
+
 /// A handle to an immutable memory mapped buffer.
 ///
 /// A `Mmap` may be backed by a file, or it can be anonymous map, backed by volatile memory. Use
@@ -339,6 +969,11 @@ impl Mmap {
     ///
     /// This is equivalent to calling `MmapOptions::new().map(file)`.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
@@ -409,6 +1044,74 @@ impl Mmap {
         self.inner.make_mut()?;
         Ok(MmapMut { inner: self.inner })
     }
+
+    /// Returns the memory map as a slice of `T`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the memory map is not aligned for `T`, or when its
+    /// length is not a multiple of the size of `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mmap = MmapOptions::new().len(4096).map_anon()?.make_read_only()?;
+    /// let values: &[u64] = mmap.view()?;
+    /// assert_eq!(512, values.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn view<T: FromBytes>(&self) -> result::Result<&[T], ViewError> {
+        view::view(self, 0, self.len())
+    }
+
+    /// Returns a range of the memory map as a slice of `T`.
+    ///
+    /// The range starts at `offset` and is `len` bytes long.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the memory map, when
+    /// `offset` is not aligned for `T`, or when `len` is not a multiple of the size of `T`.
+    pub fn view_range<T: FromBytes>(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> result::Result<&[T], ViewError> {
+        view::view(self, offset, len)
+    }
+
+    /// Checks whether the file backing the memory map has changed since it was mapped.
+    ///
+    /// File-backed memory maps record the file's identity, length and modification time when they
+    /// are created. This method compares them with the file's current state. Changes are
+    /// reported in order of precedence: a file which has been replaced is reported as
+    /// [`Replaced`] regardless of its length, and a file which has been truncated or grown is
+    /// not also reported as [`Modified`].
+    ///
+    /// The memory map retains a duplicate of the file handle, through which the file's current
+    /// state is read. To detect replaced files, it also records the path the file was opened
+    /// through, which is resolved on Linux, Android, macOS and iOS when the memory map is
+    /// created; the file-backed map constructors return an error when it can not be resolved.
+    /// Files without a path, such as unlinked temporary files and memory files, are never
+    /// reported as replaced, and neither are files on other platforms, including Windows.
+    /// Modifications are detected through the file's modification time, so they are subject to
+    /// the granularity of the file system's timestamps; writes through a writeable memory map of
+    /// the file also count as modifications.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the memory map is not backed by a file, or when the
+    /// file's metadata can not be read.
+    ///
+    /// [`Replaced`]: BackingState::Replaced
+    /// [`Modified`]: BackingState::Modified
+    pub fn check_backing(&self) -> Result<BackingState> {
+        self.inner.check_backing()
+    }
 }
 
 impl Deref for Mmap {
@@ -472,6 +1175,11 @@ impl MmapMut {
     ///
     /// This is equivalent to calling `MmapOptions::new().map_mut(file)`.
     ///
+    /// # Safety
+    ///
+    /// The underlying file must not be modified while the memory map is alive. See the
+    /// [`MmapOptions`] type-level safety documentation for details.
+    ///
     /// # Errors
     ///
     /// This method returns an error when the underlying system call fails, which can happen for a
@@ -593,14 +1301,92 @@ impl MmapMut {
         self.inner.flush_async(offset, len)
     }
 
-    /// Returns an immutable version of this memory mapped buffer.
+    /// Resizes the memory map to `new_len` bytes.
     ///
-    /// If the memory map is file-backed, the file must have been opened with read permissions.
+    /// Growing the memory map extends it further into the underlying file or anonymous memory;
+    /// shrinking it unmaps the tail. The contents of the retained part of the memory map are
+    /// preserved, and no page tables need to be rebuilt. If the memory map is moved, pointers
+    /// into it are invalidated.
+    ///
+    /// Only supported on Linux.
     ///
     /// # Errors
     ///
-    /// This method returns an error when the underlying system call fails, which can happen for a
-    /// variety of reasons, such as when the file has not been opened with read permissions.
+    /// This method returns an error when `new_len` is 0, or when the underlying system call
+    /// fails, which can happen for a variety of reasons, such as when the policy is
+    /// [`RemapPolicy::InPlace`] and the memory map can not be grown in place.
+    ///
+    /// # Safety
+    ///
+    /// If the memory map is file-backed, the file must be long enough to back the resized memory
+    /// map before the added range is accessed. Use [`resize_file()`] to extend the file as
+    /// necessary.
+    ///
+    /// Shared anonymous memory maps (the default for [`MmapOptions::map_anon()`]) are backed by a
+    /// shared memory object of a fixed size, so the added range of a grown shared anonymous
+    /// memory map must not be accessed. Create the memory map with [`MmapOptions::private()`] if
+    /// it needs to grow.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::{MmapOptions, RemapPolicy};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut mmap = MmapOptions::new().private().len(4096).map_anon()?;
+    /// mmap[0] = 42;
+    ///
+    /// unsafe { mmap.resize(8192, RemapPolicy::MayMove)? };
+    /// assert_eq!(8192, mmap.len());
+    /// assert_eq!(42, mmap[0]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`resize_file()`]: MmapMut::resize_file()
+    #[cfg(target_os = "linux")]
+    pub unsafe fn resize(&mut self, new_len: usize, policy: RemapPolicy) -> Result<()> {
+        self.inner.remap(new_len, policy == RemapPolicy::MayMove)
+    }
+
+    /// Resizes the file-backed memory map to `new_len` bytes, first extending the file if it is
+    /// too short to back the resized memory map.
+    ///
+    /// The file is never shrunk.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when `new_len` is 0, or when the underlying system calls
+    /// fail, which can happen for a variety of reasons, such as when the file has not been
+    /// opened with write permissions.
+    ///
+    /// # Safety
+    ///
+    /// `file` must be the file backing the memory map.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn resize_file(
+        &mut self,
+        file: &File,
+        new_len: usize,
+        policy: RemapPolicy,
+    ) -> Result<()> {
+        let file_len = self.inner.offset() + new_len as u64;
+        if file.metadata()?.len() < file_len {
+            file.set_len(file_len)?;
+        }
+        self.resize(new_len, policy)
+    }
+
+    /// Returns an immutable version of this memory mapped buffer.
+    ///
+    /// If the memory map is file-backed, the file must have been opened with read permissions.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system call fails, which can happen for a
+    /// variety of reasons, such as when the file has not been opened with read permissions.
     ///
     /// # Example
     ///
@@ -638,6 +1424,157 @@ impl MmapMut {
         self.inner.make_exec()?;
         Ok(Mmap { inner: self.inner })
     }
+
+    /// Returns the memory map as a slice of `T`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the memory map is not aligned for `T`, or when its
+    /// length is not a multiple of the size of `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mmap = MmapOptions::new().len(4096).map_anon()?;
+    /// let values: &[u64] = mmap.view()?;
+    /// assert_eq!(512, values.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn view<T: FromBytes>(&self) -> result::Result<&[T], ViewError> {
+        view::view(self, 0, self.len())
+    }
+
+    /// Returns a range of the memory map as a slice of `T`.
+    ///
+    /// The range starts at `offset` and is `len` bytes long.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the memory map, when
+    /// `offset` is not aligned for `T`, or when `len` is not a multiple of the size of `T`.
+    pub fn view_range<T: FromBytes>(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> result::Result<&[T], ViewError> {
+        view::view(self, offset, len)
+    }
+
+    /// Returns the memory map as a mutable slice of `T`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the memory map is not aligned for `T`, or when its
+    /// length is not a multiple of the size of `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut mmap = MmapOptions::new().len(4096).map_anon()?;
+    /// mmap.view_mut::<u32>()?[1] = 0x01020304u32.to_be();
+    /// assert_eq!(&[1, 2, 3, 4], &mmap[4..8]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn view_mut<T: FromBytes + AsBytes>(&mut self) -> result::Result<&mut [T], ViewError> {
+        let len = self.len();
+        view::view_mut(self, 0, len)
+    }
+
+    /// Returns a range of the memory map as a mutable slice of `T`.
+    ///
+    /// The range starts at `offset` and is `len` bytes long.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the memory map, when
+    /// `offset` is not aligned for `T`, or when `len` is not a multiple of the size of `T`.
+    pub fn view_range_mut<T: FromBytes + AsBytes>(
+        &mut self,
+        offset: usize,
+        len: usize,
+    ) -> result::Result<&mut [T], ViewError> {
+        view::view_mut(self, offset, len)
+    }
+
+    /// Returns the atomic of type `A` at `offset` in the memory map.
+    ///
+    /// The atomic is usable through a shared reference, so it can be shared between threads, and
+    /// between processes when the memory map is shared (see [`MmapOptions::shared()`],
+    /// `MmapOptions::map_memfd()`, and `SharedMemory`). Only atomic types which the target
+    /// supports natively implement [`Atomic`]; these are lock-free, as required for atomic
+    /// operations to be coherent between processes.
+    ///
+    /// # Safety
+    ///
+    /// The memory map dereferences to a slice of bytes, which the compiler assumes is not
+    /// modified while it is borrowed. While the returned atomic is in use, its bytes must not be
+    /// accessed other than through atomics: the caller must not read or write them through the
+    /// memory map's slice (including through [`view()`](Self::view()) and the other methods
+    /// borrowing it), and must not hold a slice covering them. Other processes sharing the memory
+    /// must likewise only access the bytes atomically.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the atomic is out of the bounds of the memory map, or
+    /// when `offset` is not aligned for `A`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mmap = MmapOptions::new().len(4096).map_anon()?;
+    /// // The bytes of the counter are only accessed through the atomic.
+    /// let counter = unsafe { mmap.atomic::<AtomicU64>(8)? };
+    /// counter.fetch_add(1, Ordering::Relaxed);
+    /// assert_eq!(1, counter.load(Ordering::Relaxed));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn atomic<A: Atomic>(&self, offset: usize) -> result::Result<&A, ViewError> {
+        self.atomic_slice(offset, mem::size_of::<A>())
+            .map(|atomics| &atomics[0])
+    }
+
+    /// Returns a range of the memory map as a slice of atomics of type `A`.
+    ///
+    /// The range starts at `offset` and is `len` bytes long. See [`MmapMut::atomic()`] for the
+    /// requirements on atomics in memory maps.
+    ///
+    /// # Safety
+    ///
+    /// See [`MmapMut::atomic()`]. The requirements apply to every byte of the range.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the memory map, when
+    /// `offset` is not aligned for `A`, or when `len` is not a multiple of the size of `A`.
+    pub unsafe fn atomic_slice<A: Atomic>(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> result::Result<&[A], ViewError> {
+        // Derive the pointer from the mapping itself, without borrowing the slice of the memory
+        // map, through which the bytes are read-only.
+        view::view_atomic(self.inner.ptr() as *mut u8, self.inner.len(), offset, len)
+    }
+
+    /// Checks whether the file backing the memory map has changed since it was mapped.
+    ///
+    /// See [`Mmap::check_backing()`].
+    pub fn check_backing(&self) -> Result<BackingState> {
+        self.inner.check_backing()
+    }
 }
 
 impl Deref for MmapMut {
@@ -679,6 +1616,210 @@ impl fmt::Debug for MmapMut {
     }
 }
 
+/// A handle to a raw memory mapped buffer.
+///
+/// Unlike [`Mmap`] and [`MmapMut`], a `MmapRaw` does not dereference to a slice. Creating a
+/// reference to memory which another process mutates concurrently is undefined behavior, so a
+/// `MmapRaw` only exposes raw pointers, volatile reads and writes, and copies in and out of the
+/// memory map. This makes it the appropriate type for memory shared with other processes.
+///
+/// A `MmapRaw` is created with [`MmapOptions::map_raw()`] or [`MmapOptions::map_raw_anon()`],
+/// or by converting a [`Mmap`] or [`MmapMut`]. A `MmapRaw` converted from a read-only [`Mmap`]
+/// remains read-only: writing to it returns an error.
+///
+/// Reads and writes through a `MmapRaw` are not synchronized: concurrent writers must
+/// coordinate through atomics or other synchronization, and may otherwise observe torn values.
+///
+/// The memory map is unmapped when the `MmapRaw` is dropped.
+pub struct MmapRaw {
+    inner: MmapInner,
+    writeable: bool,
+}
+
+impl MmapRaw {
+    /// Returns a raw pointer to the memory map.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.inner.ptr()
+    }
+
+    /// Returns a raw mutable pointer to the memory map.
+    ///
+    /// Writing through the pointer is only valid if the memory map is writeable; memory maps
+    /// converted from a read-only [`Mmap`] are not.
+    #[inline]
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.inner.ptr() as *mut u8
+    }
+
+    /// Returns the length of the memory map in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the memory map is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    /// Returns `true` if the memory map can be written to through [`write_volatile()`] and
+    /// [`copy_from_slice()`].
+    ///
+    /// A memory map converted from a read-only [`Mmap`] is not writeable.
+    ///
+    /// [`write_volatile()`]: MmapRaw::write_volatile()
+    /// [`copy_from_slice()`]: MmapRaw::copy_from_slice()
+    #[inline]
+    pub fn is_writeable(&self) -> bool {
+        self.writeable
+    }
+
+    /// Performs a volatile read of the value of type `T` at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the value is out of the bounds of the memory map, or
+    /// when `offset` is not aligned for `T`.
+    pub fn read_volatile<T: FromBytes>(&self, offset: usize) -> result::Result<T, ViewError> {
+        view::check::<T>(self.as_ptr(), self.len(), offset, mem::size_of::<T>())?;
+        unsafe { Ok(ptr::read_volatile(self.as_ptr().add(offset) as *const T)) }
+    }
+
+    /// Performs a volatile write of `value` at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the memory map is not writeable, when the value is out
+    /// of the bounds of the memory map, or when `offset` is not aligned for `T`.
+    pub fn write_volatile<T: AsBytes>(
+        &self,
+        offset: usize,
+        value: T,
+    ) -> result::Result<(), ViewError> {
+        if !self.writeable {
+            return Err(ViewError::ReadOnly);
+        }
+        view::check::<T>(self.as_ptr(), self.len(), offset, mem::size_of::<T>())?;
+        unsafe { ptr::write_volatile(self.as_mut_ptr().add(offset) as *mut T, value) };
+        Ok(())
+    }
+
+    /// Copies bytes starting at `offset` in the memory map into `dst`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the memory map.
+    pub fn copy_to_slice(&self, offset: usize, dst: &mut [u8]) -> Result<()> {
+        self.check_range(offset, dst.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr().add(offset), dst.as_mut_ptr(), dst.len());
+        }
+        Ok(())
+    }
+
+    /// Copies the bytes of `src` into the memory map, starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error of kind `PermissionDenied` when the memory map is not
+    /// writeable, or an error when the range is out of the bounds of the memory map.
+    pub fn copy_from_slice(&self, offset: usize, src: &[u8]) -> Result<()> {
+        if !self.writeable {
+            return Err(ViewError::ReadOnly.into());
+        }
+        self.check_range(offset, src.len())?;
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), self.as_mut_ptr().add(offset), src.len());
+        }
+        Ok(())
+    }
+
+    fn check_range(&self, offset: usize, len: usize) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "range is out of the bounds of the memory map",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Flushes outstanding memory map modifications to disk.
+    ///
+    /// See [`MmapMut::flush()`].
+    pub fn flush(&self) -> Result<()> {
+        self.inner.flush(0, self.len())
+    }
+
+    /// Asynchronously flushes outstanding memory map modifications to disk.
+    ///
+    /// See [`MmapMut::flush_async()`].
+    pub fn flush_async(&self) -> Result<()> {
+        self.inner.flush_async(0, self.len())
+    }
+
+    /// Flushes outstanding memory map modifications in the range to disk.
+    ///
+    /// See [`MmapMut::flush_range()`].
+    pub fn flush_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.inner.flush(offset, len)
+    }
+
+    /// Asynchronously flushes outstanding memory map modifications in the range to disk.
+    ///
+    /// See [`MmapMut::flush_async_range()`].
+    pub fn flush_async_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.inner.flush_async(offset, len)
+    }
+}
+
+impl From<Mmap> for MmapRaw {
+    fn from(mmap: Mmap) -> MmapRaw {
+        MmapRaw {
+            inner: mmap.inner,
+            writeable: false,
+        }
+    }
+}
+
+impl From<MmapMut> for MmapRaw {
+    fn from(mmap: MmapMut) -> MmapRaw {
+        MmapRaw {
+            inner: mmap.inner,
+            writeable: true,
+        }
+    }
+}
+
+impl fmt::Debug for MmapRaw {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("MmapRaw")
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Converts a range of a memory map of length `map_len` to an offset and length.
+fn range_bounds<R: RangeBounds<usize>>(range: R, map_len: usize) -> Result<(usize, usize)> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => Some(map_len),
+    };
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => Ok((start, end - start)),
+        _ => Err(Error::new(ErrorKind::InvalidInput, "range is invalid")),
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -686,8 +1827,11 @@ mod test {
     #[cfg(windows)]
     extern crate winapi;
 
-    use std::fs::OpenOptions;
-    use std::io::{Read, Write};
+    #[cfg(unix)]
+    use libc;
+
+    use std::fs::{self, OpenOptions};
+    use std::io::{ErrorKind, Read, Write};
     #[cfg(windows)]
     use std::os::windows::fs::OpenOptionsExt;
     use std::sync::Arc;
@@ -696,7 +1840,13 @@ mod test {
     #[cfg(windows)]
     use winapi::um::winnt::GENERIC_ALL;
 
-    use super::{Mmap, MmapMut, MmapOptions};
+    #[cfg(unix)]
+    use super::Advice;
+    #[cfg(target_os = "linux")]
+    use super::UncheckedAdvice;
+    #[cfg(target_os = "linux")]
+    use super::{HugePageSize, RemapPolicy};
+    use super::{LockMode, LockWait, Mmap, MmapMut, MmapOptions, MmapRaw, ViewError};
 
     #[test]
     fn map_file() {
@@ -708,6 +1858,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
 
@@ -740,6 +1891,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
         let mmap = unsafe { Mmap::map(&file) };
@@ -766,11 +1918,247 @@ mod test {
         assert_eq!(&incr[..], &mmap[..]);
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn map_anon_private_shared() {
+        let mut shared = MmapOptions::new().len(128).shared().map_anon().unwrap();
+        let mut private = MmapOptions::new().len(128).private().map_anon().unwrap();
+
+        unsafe {
+            let pid = libc::fork();
+            assert!(pid >= 0, "fork failed");
+            if pid == 0 {
+                shared[0] = 42;
+                private[0] = 42;
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            assert_eq!(pid, libc::waitpid(pid, &mut status, 0));
+        }
+
+        // Only writes to the shared memory map are visible across the fork.
+        assert_eq!(42, shared[0]);
+        assert_eq!(0, private[0]);
+    }
+
     #[test]
     fn map_anon_zero_len() {
         assert!(MmapOptions::new().map_anon().is_err())
     }
 
+    /// Returns the number of free huge pages of `size` bytes in the hugetlb pool, or `None` if
+    /// the huge page size is not supported.
+    #[cfg(target_os = "linux")]
+    pub fn free_huge_pages(size: usize) -> Option<usize> {
+        let path = format!(
+            "/sys/kernel/mm/hugepages/hugepages-{}kB/free_hugepages",
+            size / 1024
+        );
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|free| free.trim().parse().ok())
+    }
+
+    /// Asserts that creating a huge page memory map failed because the hugetlb pool is empty.
+    #[cfg(target_os = "linux")]
+    pub fn assert_hugetlb_pool_empty(error: &::std::io::Error) {
+        assert_eq!(::std::io::ErrorKind::Other, error.kind());
+        assert!(error.to_string().contains("hugetlb pool"), "{}", error);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn map_anon_huge_pages() {
+        let huge_page_size = 2 << 20;
+        let mmap = MmapOptions::new()
+            .len(4096)
+            .huge_pages(HugePageSize::Size2MiB)
+            .map_anon();
+        match free_huge_pages(huge_page_size) {
+            Some(0) => assert_hugetlb_pool_empty(&mmap.unwrap_err()),
+            Some(_) => {
+                let mut mmap = mmap.unwrap();
+                assert_eq!(huge_page_size, mmap.len());
+                mmap[huge_page_size - 1] = 42;
+                assert_eq!(42, mmap[huge_page_size - 1]);
+            }
+            None => assert!(mmap.is_err()),
+        }
+    }
+
+    #[test]
+    fn map_populate() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+
+        let mut mmap = unsafe { MmapOptions::new().populate().map_mut(&file).unwrap() };
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+
+        let mut mmap = MmapOptions::new().len(128).populate().map_anon().unwrap();
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+
+        let mmap = unsafe { MmapOptions::new().populate().map(&file).unwrap() };
+        assert_eq!(42, mmap[0]);
+
+        // Populating pages past the end of the file fails instead of being silently skipped.
+        #[cfg(target_os = "linux")]
+        {
+            let mmap = unsafe { MmapOptions::new().len(1 << 20).populate().map(&file) };
+            assert!(mmap.is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn map_populate_write() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+
+        let mut mmap = unsafe { MmapOptions::new().populate_write().map_mut(&file).unwrap() };
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+
+        let mut mmap = MmapOptions::new()
+            .len(128)
+            .populate_write()
+            .map_anon()
+            .unwrap();
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+
+        // Populating pages past the end of the file fails instead of being silently skipped.
+        let mmap = unsafe {
+            MmapOptions::new()
+                .len(1 << 20)
+                .populate_write()
+                .map_mut(&file)
+        };
+        assert!(mmap.is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn advise() {
+        let mut mmap = MmapMut::map_anon(4 * 4096).unwrap();
+        mmap[0] = 42;
+
+        mmap.advise(Advice::Sequential).unwrap();
+        mmap.advise(Advice::WillNeed).unwrap();
+        mmap.advise_range(Advice::Random, 4096 + 13, 4096).unwrap();
+        mmap.advise(Advice::Normal).unwrap();
+        assert_eq!(42, mmap[0]);
+
+        assert!(mmap.advise_range(Advice::Normal, 4096, 4 * 4096).is_err());
+        assert!(mmap.advise_range(Advice::Normal, usize::MAX, 2).is_err());
+
+        let mmap = mmap.make_read_only().unwrap();
+        mmap.advise(Advice::Random).unwrap();
+        assert_eq!(42, mmap[0]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn unchecked_advise_dont_need() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+
+        let mut mmap = unsafe { MmapOptions::new().map_copy(&file).unwrap() };
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+
+        // Dropping the private pages reverts the memory map to the contents of the file.
+        unsafe { mmap.unchecked_advise(UncheckedAdvice::DontNeed).unwrap() };
+        assert_eq!(0, mmap[0]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn resize_anon() {
+        let mut mmap = MmapOptions::new().private().len(4096).map_anon().unwrap();
+        mmap[4095] = 42;
+
+        unsafe { mmap.resize(3 * 4096, RemapPolicy::MayMove).unwrap() };
+        assert_eq!(3 * 4096, mmap.len());
+        assert_eq!(42, mmap[4095]);
+        mmap[3 * 4096 - 1] = 43;
+
+        unsafe { mmap.resize(4096, RemapPolicy::InPlace).unwrap() };
+        assert_eq!(4096, mmap.len());
+        assert_eq!(42, mmap[4095]);
+
+        assert!(unsafe { mmap.resize(0, RemapPolicy::MayMove) }.is_err());
+        assert_eq!(4096, mmap.len());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn resize_file() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+
+        // Use an unaligned offset to exercise the alignment adjustment.
+        let mut mmap = unsafe { MmapOptions::new().offset(100).map_mut(&file).unwrap() };
+        assert_eq!(28, mmap.len());
+        mmap[0] = 42;
+
+        unsafe {
+            mmap.resize_file(&file, 10000, RemapPolicy::MayMove)
+                .unwrap()
+        };
+        assert_eq!(10000, mmap.len());
+        assert_eq!(10100, file.metadata().unwrap().len());
+        assert_eq!(42, mmap[0]);
+        mmap[9999] = 43;
+        mmap.flush().unwrap();
+
+        // Shrinking the memory map leaves the file untouched.
+        unsafe { mmap.resize_file(&file, 10, RemapPolicy::InPlace).unwrap() };
+        assert_eq!(10, mmap.len());
+        assert_eq!(10100, file.metadata().unwrap().len());
+
+        let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+        assert_eq!(42, mmap[100]);
+        assert_eq!(43, mmap[10099]);
+    }
+
     #[test]
     fn file_write() {
         let tempdir = tempdir::TempDir::new("mmap").unwrap();
@@ -780,6 +2168,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
         file.set_len(128).unwrap();
@@ -791,7 +2180,7 @@ mod test {
         (&mut mmap[..]).write_all(write).unwrap();
         mmap.flush().unwrap();
 
-        file.read(&mut read).unwrap();
+        file.read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
     }
 
@@ -804,6 +2193,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
         file.set_len(128).unwrap();
@@ -829,6 +2219,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
         file.set_len(128).unwrap();
@@ -839,20 +2230,20 @@ mod test {
 
         let mut mmap = unsafe { MmapOptions::new().map_copy(&file).unwrap() };
 
-        (&mut mmap[..]).write(write).unwrap();
+        (&mut mmap[..]).write_all(write).unwrap();
         mmap.flush().unwrap();
 
         // The mmap contains the write
-        (&mmap[..]).read(&mut read).unwrap();
+        (&mmap[..]).read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
 
         // The file does not contain the write
-        file.read(&mut read).unwrap();
+        file.read_exact(&mut read).unwrap();
         assert_eq!(nulls, &read);
 
         // another mmap does not contain the write
         let mmap2 = unsafe { MmapOptions::new().map(&file).unwrap() };
-        (&mmap2[..]).read(&mut read).unwrap();
+        (&mmap2[..]).read_exact(&mut read).unwrap();
         assert_eq!(nulls, &read);
     }
 
@@ -865,10 +2256,11 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&path)
             .unwrap();
 
-        let offset = u32::max_value() as u64 + 2;
+        let offset = u32::MAX as u64 + 2;
         let len = 5432;
         file.set_len(offset + len as u64).unwrap();
 
@@ -907,6 +2299,7 @@ mod test {
     }
 
     #[test]
+    #[allow(unused_must_use)]
     fn sync_send() {
         let mmap = Arc::new(MmapMut::map_anon(129).unwrap());
         thread::spawn(move || {
@@ -948,7 +2341,7 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
-            .open(&tempdir.path().join("jit_x86"))
+            .open(tempdir.path().join("jit_x86"))
             .expect("open");
 
         file.set_len(4096).expect("set_len");
@@ -970,7 +2363,7 @@ mod test {
             .create(true)
             .open(&path)
             .expect("open");
-        file.set_len(256 as u64).expect("set_len");
+        file.set_len(256).expect("set_len");
 
         let mmap = unsafe { MmapMut::map_mut(&file).expect("map_mut") };
 
@@ -980,20 +2373,20 @@ mod test {
         let write = b"abc123";
         let mut read = [0u8; 6];
 
-        (&mut mmap[..]).write(write).unwrap();
+        (&mut mmap[..]).write_all(write).unwrap();
         mmap.flush().unwrap();
 
         // The mmap contains the write
-        (&mmap[..]).read(&mut read).unwrap();
+        (&mmap[..]).read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
 
         // The file should contain the write
-        file.read(&mut read).unwrap();
+        file.read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
 
         // another mmap should contain the write
         let mmap2 = unsafe { MmapOptions::new().map(&file).unwrap() };
-        (&mmap2[..]).read(&mut read).unwrap();
+        (&mmap2[..]).read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
 
         let mmap = mmap.make_exec().expect("make_exec");
@@ -1016,7 +2409,7 @@ mod test {
             .create(true)
             .open(&path)
             .expect("open");
-        file.set_len(256 as u64).expect("set_len");
+        file.set_len(256).expect("set_len");
 
         let mmap = unsafe { MmapOptions::new().map_copy(&file).expect("map_mut") };
 
@@ -1027,20 +2420,20 @@ mod test {
         let write = b"abc123";
         let mut read = [0u8; 6];
 
-        (&mut mmap[..]).write(write).unwrap();
+        (&mut mmap[..]).write_all(write).unwrap();
         mmap.flush().unwrap();
 
         // The mmap contains the write
-        (&mmap[..]).read(&mut read).unwrap();
+        (&mmap[..]).read_exact(&mut read).unwrap();
         assert_eq!(write, &read);
 
         // The file does not contain the write
-        file.read(&mut read).unwrap();
+        file.read_exact(&mut read).unwrap();
         assert_eq!(nulls, &read);
 
         // another mmap does not contain the write
         let mmap2 = unsafe { MmapOptions::new().map(&file).unwrap() };
-        (&mmap2[..]).read(&mut read).unwrap();
+        (&mmap2[..]).read_exact(&mut read).unwrap();
         assert_eq!(nulls, &read);
 
         let mmap = mmap.make_exec().expect("make_exec");
@@ -1057,4 +2450,234 @@ mod test {
         let mmap = mmap.make_exec().expect("make_exec");
         drop(mmap);
     }
+
+    #[test]
+    fn map_raw() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(16).unwrap();
+
+        let raw = MmapOptions::new().map_raw(&file).unwrap();
+        assert_eq!(16, raw.len());
+        raw.copy_from_slice(4, b"abcd").unwrap();
+        raw.write_volatile(8, u64::MAX).unwrap();
+        raw.flush().unwrap();
+
+        let mut buf = [0; 4];
+        raw.copy_to_slice(4, &mut buf).unwrap();
+        assert_eq!(b"abcd", &buf);
+        assert_eq!(u64::MAX, raw.read_volatile::<u64>(8).unwrap());
+        assert_eq!(
+            b"\0\0\0\0abcd\xff\xff\xff\xff\xff\xff\xff\xff",
+            &fs::read(&path).unwrap()[..]
+        );
+
+        assert!(raw.read_volatile::<u64>(4).is_err());
+        assert!(raw.write_volatile(16, 0u8).is_err());
+        assert!(raw.copy_from_slice(13, b"abcd").is_err());
+        assert!(raw.copy_to_slice(usize::MAX, &mut buf).is_err());
+    }
+
+    #[test]
+    fn map_raw_anon() {
+        let raw = MmapOptions::new().len(8).map_raw_anon().unwrap();
+        raw.write_volatile(0, 1u32).unwrap();
+        assert_eq!(1, raw.read_volatile::<u32>(0).unwrap());
+        unsafe { *raw.as_mut_ptr().add(4) = 2 };
+        assert_eq!(2, raw.read_volatile::<u8>(4).unwrap());
+
+        let mmap = MmapOptions::new().len(8).map_anon().unwrap();
+        let raw = MmapRaw::from(mmap.make_read_only().unwrap());
+        assert!(!raw.is_writeable());
+        assert_eq!(0, raw.read_volatile::<u64>(0).unwrap());
+        assert_eq!(Err(ViewError::ReadOnly), raw.write_volatile(0, 1u64));
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            raw.copy_from_slice(0, b"abcd").unwrap_err().kind()
+        );
+        assert_eq!(0, raw.read_volatile::<u64>(0).unwrap());
+    }
+
+    #[test]
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        windows
+    ))]
+    fn lock() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+        let open = || {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap()
+        };
+
+        let shared = || {
+            let mut options = MmapOptions::new();
+            options.lock(LockMode::Shared, LockWait::NonBlocking);
+            options
+        };
+        let exclusive = || {
+            let mut options = MmapOptions::new();
+            options.lock(LockMode::Exclusive, LockWait::NonBlocking);
+            options
+        };
+
+        // Shared locks do not conflict with each other.
+        let first = unsafe { shared().map(&file).unwrap() };
+        let second = unsafe { shared().map_copy(&open()).unwrap() };
+        assert!(unsafe { exclusive().map_mut(&open()) }.is_err());
+
+        // Each memory map holds its own lock, which outlives the file handle it was taken
+        // through.
+        drop(first);
+        assert!(unsafe { exclusive().map_mut(&open()) }.is_err());
+        drop(second);
+
+        // Memory maps created from the same file handle do not share a lock.
+        let first = unsafe { shared().map(&file).unwrap() };
+        let second = unsafe { shared().map(&file).unwrap() };
+        drop(first);
+        assert!(unsafe { exclusive().map_mut(&open()) }.is_err());
+        drop(second);
+
+        let mmap = unsafe { exclusive().map_mut(&open()).unwrap() };
+        assert!(unsafe { shared().map(&open()) }.is_err());
+        assert!(unsafe { shared().map_exec(&open()) }.is_err());
+        // Anonymous memory maps are not locked.
+        exclusive().len(128).map_anon().unwrap();
+
+        // The lock is carried through conversions.
+        let mmap = mmap.make_read_only().unwrap();
+        assert!(MmapOptions::new()
+            .lock(LockMode::Shared, LockWait::NonBlocking)
+            .map_raw(&open())
+            .is_err());
+        drop(mmap);
+        MmapOptions::new()
+            .lock(LockMode::Exclusive, LockWait::Blocking)
+            .map_raw(&open())
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(all(
+        unix,
+        not(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios"
+        ))
+    ))]
+    fn lock_unsupported() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tempdir.path().join("mmap"))
+            .unwrap();
+        file.set_len(128).unwrap();
+        assert!(unsafe {
+            MmapOptions::new()
+                .lock(LockMode::Shared, LockWait::NonBlocking)
+                .map(&file)
+        }
+        .is_err());
+    }
+
+    #[test]
+    fn check_backing() {
+        use std::time::{Duration, SystemTime};
+
+        use super::BackingState;
+
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(128).unwrap();
+        let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        file.set_modified(epoch).unwrap();
+
+        let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+        drop(file);
+        assert_eq!(BackingState::Unchanged, mmap.check_backing().unwrap());
+
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(epoch + Duration::from_secs(1)).unwrap();
+        assert_eq!(BackingState::Modified, mmap.check_backing().unwrap());
+        file.set_len(256).unwrap();
+        assert_eq!(BackingState::Grown, mmap.check_backing().unwrap());
+        file.set_len(64).unwrap();
+        assert_eq!(BackingState::Truncated, mmap.check_backing().unwrap());
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios"
+        ))]
+        {
+            let other = tempdir.path().join("other");
+            fs::write(&other, [0; 128]).unwrap();
+            fs::rename(&other, &path).unwrap();
+            assert_eq!(BackingState::Replaced, mmap.check_backing().unwrap());
+            fs::remove_file(&path).unwrap();
+            assert_eq!(BackingState::Replaced, mmap.check_backing().unwrap());
+
+            // A file which is unlinked before it is mapped has no path to be replaced through.
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .unwrap();
+            file.set_len(128).unwrap();
+            fs::remove_file(&path).unwrap();
+            let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+            drop(file);
+            assert_eq!(BackingState::Unchanged, mmap.check_backing().unwrap());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let memfd = MmapOptions::new().len(128).map_memfd("memfd").unwrap();
+            let mmap = unsafe { MmapOptions::new().map(memfd.as_file()).unwrap() };
+            assert_eq!(BackingState::Unchanged, mmap.check_backing().unwrap());
+            memfd.as_file().set_len(256).unwrap();
+            assert_eq!(BackingState::Grown, mmap.check_backing().unwrap());
+        }
+
+        let anon = MmapMut::map_anon(128).unwrap();
+        assert!(anon.check_backing().is_err());
+    }
 }
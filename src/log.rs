@@ -0,0 +1,594 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(unix)]
+use unix::preallocate;
+#[cfg(windows)]
+use windows::preallocate;
+use {Mmap, MmapMut, MmapOptions};
+
+/// Identifies a log segment file, and its format version.
+const MAGIC: &[u8; 8] = b"MMAPLOG1";
+
+/// The length of the segment header.
+///
+/// The header holds the magic number, followed by the committed tail offset and the sealed flag,
+/// each an aligned little-endian `u64`. Records follow the header.
+const HEADER_LEN: usize = 64;
+const TAIL_OFFSET: usize = 8;
+const SEALED_OFFSET: usize = 16;
+
+/// The length of the prefix which precedes each record.
+const LEN_PREFIX: usize = 4;
+
+/// Returns the path of the segment file with the given index.
+fn segment_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{:020}.log", index))
+}
+
+/// Returns the indexes of the segment files in the directory, in ascending order.
+fn segment_indexes(dir: &Path) -> Result<Vec<u64>> {
+    let mut indexes = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = match name.to_str() {
+            Some(name) => name,
+            None => continue,
+        };
+        if name.len() == 24 && name.ends_with(".log") {
+            if let Ok(index) = name[..20].parse() {
+                indexes.push(index);
+            }
+        }
+    }
+    indexes.sort_unstable();
+    Ok(indexes)
+}
+
+/// Returns the header field at `offset` in the memory map as an atomic.
+///
+/// Header fields are only accessed atomically, since they are shared with other processes.
+///
+/// # Safety
+///
+/// `ptr` must point to the start of a segment memory map, which must outlive the reference.
+unsafe fn header_field<'a>(ptr: *const u8, offset: usize) -> &'a AtomicU64 {
+    // The memory map is page aligned, so the field is aligned.
+    &*(ptr.add(offset) as *const AtomicU64)
+}
+
+fn tail(mmap: &Mmap) -> usize {
+    let tail = unsafe { header_field(mmap.as_ptr(), TAIL_OFFSET) };
+    u64::from_le(tail.load(Ordering::Acquire)) as usize
+}
+
+fn sealed(mmap: &Mmap) -> bool {
+    let sealed = unsafe { header_field(mmap.as_ptr(), SEALED_OFFSET) };
+    sealed.load(Ordering::Acquire) != 0
+}
+
+fn store_tail(mmap: &mut MmapMut, tail: usize) {
+    let field = unsafe { header_field(mmap.as_mut_ptr(), TAIL_OFFSET) };
+    field.store((tail as u64).to_le(), Ordering::Release);
+}
+
+/// Marks the segment as sealed, and flushes its header.
+fn seal(mmap: &mut MmapMut) -> Result<()> {
+    let sealed = unsafe { header_field(mmap.as_mut_ptr(), SEALED_OFFSET) };
+    sealed.store(1, Ordering::Release);
+    mmap.flush_range(0, HEADER_LEN)
+}
+
+fn check_magic(segment: &[u8], index: u64) -> Result<()> {
+    if segment.len() < HEADER_LEN || &segment[..MAGIC.len()] != MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("log segment {} has an invalid header", index),
+        ));
+    }
+    Ok(())
+}
+
+/// An append-only log of records, stored in a directory of memory mapped segment files.
+///
+/// Records are arbitrary byte strings. They are appended to the current segment with
+/// [`append()`], and become durable and visible to readers when they are committed with
+/// [`commit()`]. Each segment file records the offset of its committed tail in a header, so
+/// after a crash the log resumes after the last committed record, and uncommitted records are
+/// discarded.
+///
+/// Segment files are preallocated to the configured segment length. When a record does not fit
+/// in the current segment, the log rolls over: the current segment is committed and sealed, and
+/// a new segment is created.
+///
+/// Committed records can be read, from this or another process, with a [`MmapLogReader`].
+///
+/// ## Safety
+///
+/// A log is opened with an `unsafe` method, because its segment files are file-backed memory
+/// maps. There must be at most one writer for a log directory, and the segment files must not be
+/// modified other than through the writer. See [`MmapOptions`] for details.
+///
+/// ## Example
+///
+/// ```
+/// # extern crate memmap;
+/// # extern crate tempdir;
+/// #
+/// use memmap::{MmapLog, MmapLogReader};
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let tempdir = tempdir::TempDir::new("mmap")?;
+/// # let dir = tempdir.path();
+/// let mut log = unsafe { MmapLog::open(dir, 1 << 20)? };
+/// log.append(b"first")?;
+/// log.append(b"second")?;
+/// log.commit()?;
+///
+/// let mut reader = unsafe { MmapLogReader::open(dir)? };
+/// assert_eq!(Some(&b"first"[..]), reader.next_record()?);
+/// assert_eq!(Some(&b"second"[..]), reader.next_record()?);
+/// assert_eq!(None, reader.next_record()?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`append()`]: MmapLog::append()
+/// [`commit()`]: MmapLog::commit()
+/// [`MmapOptions`]: crate::MmapOptions
+pub struct MmapLog {
+    dir: PathBuf,
+    segment_len: usize,
+    index: u64,
+    mmap: MmapMut,
+    /// The end of the committed records in the current segment.
+    committed: usize,
+    /// The end of the appended records in the current segment.
+    position: usize,
+}
+
+impl MmapLog {
+    /// Opens the log in directory `dir`, creating the directory and the first segment if
+    /// necessary.
+    ///
+    /// New segment files are `segment_len` bytes long, which limits the length of a record to
+    /// `segment_len` less 68 bytes of header and length prefix. If the log already exists,
+    /// appending resumes after the last committed record of its last segment, and segments
+    /// before the last one which were left unsealed by a crash during rollover are sealed.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the segment length is too short to hold a record, when
+    /// an existing segment is corrupt, or when the underlying file system operations fail.
+    ///
+    /// # Safety
+    ///
+    /// There must be no other writer for the log, and its segment files must not be modified
+    /// other than through this writer. See the [`MmapOptions`] type-level safety documentation
+    /// for details.
+    ///
+    /// [`MmapOptions`]: crate::MmapOptions
+    pub unsafe fn open<P: AsRef<Path>>(dir: P, segment_len: usize) -> Result<MmapLog> {
+        if segment_len <= HEADER_LEN + LEN_PREFIX {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "log segment length is too short to hold a record",
+            ));
+        }
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let indexes = segment_indexes(&dir)?;
+        if let Some((_, earlier)) = indexes.split_last() {
+            // A crash while rolling over leaves the previous segment unsealed after the next one
+            // was created, so readers would wait for further records in it forever.
+            for &index in earlier {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(segment_path(&dir, index))?;
+                let mut mmap = MmapOptions::new().map_mut(&file)?;
+                check_magic(&mmap, index)?;
+                if header_field(mmap.as_ptr(), SEALED_OFFSET).load(Ordering::Acquire) == 0 {
+                    seal(&mut mmap)?;
+                }
+            }
+        }
+        let (index, mmap) = match indexes.last() {
+            Some(&index) => {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(segment_path(&dir, index))?;
+                let mmap = MmapOptions::new().map_mut(&file)?;
+                check_magic(&mmap, index)?;
+                (index, mmap)
+            }
+            None => (0, create_segment(&dir, 0, segment_len)?),
+        };
+
+        let committed =
+            u64::from_le(header_field(mmap.as_ptr(), TAIL_OFFSET).load(Ordering::Acquire)) as usize;
+        if committed < HEADER_LEN || committed > mmap.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("log segment {} has an invalid tail", index),
+            ));
+        }
+        Ok(MmapLog {
+            dir,
+            segment_len,
+            index,
+            mmap,
+            committed,
+            position: committed,
+        })
+    }
+
+    /// Returns the directory of the log.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the index of the current segment.
+    pub fn segment(&self) -> u64 {
+        self.index
+    }
+
+    /// Appends a record to the log.
+    ///
+    /// The record is not durable, and is not visible to readers, until it is committed with
+    /// [`commit()`]. If the record does not fit in the current segment, the log rolls over to a
+    /// new segment, committing the records appended so far.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the record is too long to fit in a segment, or when
+    /// rolling over to a new segment fails.
+    ///
+    /// [`commit()`]: MmapLog::commit()
+    pub fn append(&mut self, record: &[u8]) -> Result<()> {
+        let len = LEN_PREFIX + record.len();
+        if record.len() > u32::MAX as usize || len > self.segment_len - HEADER_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "record is too long to fit in a log segment",
+            ));
+        }
+        if self.position + len > self.mmap.len() {
+            self.roll()?;
+        }
+
+        let position = self.position;
+        self.mmap[position..position + LEN_PREFIX]
+            .copy_from_slice(&(record.len() as u32).to_le_bytes());
+        self.mmap[position + LEN_PREFIX..position + len].copy_from_slice(record);
+        self.position += len;
+        Ok(())
+    }
+
+    /// Commits the appended records, making them durable and visible to readers.
+    ///
+    /// The records are flushed to disk before the segment's tail offset is advanced past them,
+    /// so the tail offset never covers records which have not been durably stored.
+    pub fn commit(&mut self) -> Result<()> {
+        if self.position == self.committed {
+            return Ok(());
+        }
+        self.mmap
+            .flush_range(self.committed, self.position - self.committed)?;
+        store_tail(&mut self.mmap, self.position);
+        self.mmap.flush_range(0, HEADER_LEN)?;
+        self.committed = self.position;
+        Ok(())
+    }
+
+    /// Commits and seals the current segment, and continues the log in a new segment.
+    fn roll(&mut self) -> Result<()> {
+        self.commit()?;
+        let index = self.index + 1;
+        // Readers move on to the next segment once the current one is sealed, so the next
+        // segment must exist first.
+        let mmap = create_segment(&self.dir, index, self.segment_len)?;
+        seal(&mut self.mmap)?;
+
+        self.index = index;
+        self.mmap = mmap;
+        self.committed = HEADER_LEN;
+        self.position = HEADER_LEN;
+        Ok(())
+    }
+}
+
+/// Creates and maps an empty segment file.
+///
+/// The segment is initialized under a temporary name and then renamed into place, so that
+/// readers never observe a segment without a valid header.
+fn create_segment(dir: &Path, index: u64, segment_len: usize) -> Result<MmapMut> {
+    let path = segment_path(dir, index);
+    let tmp_path = path.with_extension("log.tmp");
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp_path)?;
+    preallocate(&file, segment_len as u64)?;
+
+    let mut mmap = unsafe { MmapOptions::new().map_mut(&file)? };
+    mmap[..MAGIC.len()].copy_from_slice(MAGIC);
+    store_tail(&mut mmap, HEADER_LEN);
+    mmap.flush()?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, &path)?;
+    // Persist the rename, on platforms which allow opening directories.
+    if let Ok(dir) = File::open(dir) {
+        dir.sync_all()?;
+    }
+    Ok(mmap)
+}
+
+/// A cursor over the committed records of a [`MmapLog`].
+///
+/// The reader may live in a different process than the writer. It follows the writer as it
+/// commits new records and rolls over to new segments: when the reader has caught up with the
+/// writer, [`next_record()`] returns `None`, and returns further records once they have been
+/// committed.
+///
+/// ## Safety
+///
+/// A reader is opened with an `unsafe` method, because its segment files are file-backed memory
+/// maps. The segment files must not be modified other than through the log's writer. See
+/// [`MmapOptions`] for details.
+///
+/// [`next_record()`]: MmapLogReader::next_record()
+/// [`MmapOptions`]: crate::MmapOptions
+pub struct MmapLogReader {
+    dir: PathBuf,
+    index: u64,
+    mmap: Option<Mmap>,
+    position: usize,
+}
+
+impl MmapLogReader {
+    /// Opens a reader positioned at the first record of the log in directory `dir`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying file system operations fail.
+    ///
+    /// # Safety
+    ///
+    /// The log's segment files must not be modified other than through the log's writer. See
+    /// the [`MmapOptions`] type-level safety documentation for details.
+    ///
+    /// [`MmapOptions`]: crate::MmapOptions
+    pub unsafe fn open<P: AsRef<Path>>(dir: P) -> Result<MmapLogReader> {
+        let dir = dir.as_ref().to_path_buf();
+        let index = segment_indexes(&dir)?.first().cloned().unwrap_or(0);
+        Ok(MmapLogReader {
+            dir,
+            index,
+            mmap: None,
+            position: HEADER_LEN,
+        })
+    }
+
+    /// Returns the index of the segment the reader is positioned in.
+    pub fn segment(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the next committed record, or `None` if the reader has caught up with the
+    /// writer.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when a segment is corrupt, or when the underlying file
+    /// system operations fail.
+    pub fn next_record(&mut self) -> Result<Option<&[u8]>> {
+        loop {
+            if self.mmap.is_none() {
+                let file = match File::open(segment_path(&self.dir, self.index)) {
+                    Ok(file) => file,
+                    Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+                    Err(error) => return Err(error),
+                };
+                let mmap = unsafe { MmapOptions::new().map(&file)? };
+                check_magic(&mmap, self.index)?;
+                self.mmap = Some(mmap);
+                self.position = HEADER_LEN;
+            }
+
+            let (tail, sealed) = {
+                let mmap = self.mmap.as_ref().unwrap();
+                // Check for the seal first, so that no records committed before sealing are
+                // missed.
+                let sealed = sealed(mmap);
+                (tail(mmap).min(mmap.len()), sealed)
+            };
+
+            if self.position < tail {
+                let mmap = self.mmap.as_ref().unwrap();
+                let corrupt = || {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("log segment {} has a corrupt record", self.index),
+                    )
+                };
+                let start = self.position + LEN_PREFIX;
+                if start > tail {
+                    return Err(corrupt());
+                }
+                let mut prefix = [0; LEN_PREFIX];
+                prefix.copy_from_slice(&mmap[self.position..start]);
+                let end = match start.checked_add(u32::from_le_bytes(prefix) as usize) {
+                    Some(end) if end <= tail => end,
+                    _ => return Err(corrupt()),
+                };
+                self.position = end;
+                return Ok(Some(&mmap[start..end]));
+            }
+
+            if !sealed {
+                return Ok(None);
+            }
+            self.index += 1;
+            self.mmap = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    use std::fs::{self, OpenOptions};
+    use std::io::{ErrorKind, Seek, SeekFrom, Write};
+
+    use super::{segment_path, MmapLog, MmapLogReader, HEADER_LEN, SEALED_OFFSET, TAIL_OFFSET};
+
+    #[test]
+    fn append_commit_read() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let mut log = unsafe { MmapLog::open(tempdir.path(), 4096).unwrap() };
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+
+        log.append(b"abc").unwrap();
+        log.append(b"").unwrap();
+
+        // Records are not visible until they are committed.
+        assert_eq!(None, reader.next_record().unwrap());
+
+        log.commit().unwrap();
+        assert_eq!(Some(&b"abc"[..]), reader.next_record().unwrap());
+        assert_eq!(Some(&b""[..]), reader.next_record().unwrap());
+        assert_eq!(None, reader.next_record().unwrap());
+
+        log.append(b"123").unwrap();
+        log.commit().unwrap();
+        assert_eq!(Some(&b"123"[..]), reader.next_record().unwrap());
+        assert_eq!(None, reader.next_record().unwrap());
+    }
+
+    #[test]
+    fn roll_segments() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let mut log = unsafe { MmapLog::open(tempdir.path(), 128).unwrap() };
+
+        let records: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 20]).collect();
+        for record in &records {
+            log.append(record).unwrap();
+        }
+        log.commit().unwrap();
+        assert!(log.segment() > 0);
+        assert_eq!(
+            log.segment() as usize + 1,
+            fs::read_dir(tempdir.path()).unwrap().count()
+        );
+
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+        for record in &records {
+            assert_eq!(Some(&record[..]), reader.next_record().unwrap());
+        }
+        assert_eq!(None, reader.next_record().unwrap());
+        assert_eq!(log.segment(), reader.segment());
+
+        // Records which do not fit in a segment are rejected.
+        assert!(log.append(&[0; 61]).is_err());
+        log.append(&[0; 60]).unwrap();
+    }
+
+    #[test]
+    fn reopen() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        {
+            let mut log = unsafe { MmapLog::open(tempdir.path(), 4096).unwrap() };
+            log.append(b"committed").unwrap();
+            log.commit().unwrap();
+            log.append(b"uncommitted").unwrap();
+        }
+
+        // Appending resumes after the last committed record.
+        let mut log = unsafe { MmapLog::open(tempdir.path(), 4096).unwrap() };
+        log.append(b"appended").unwrap();
+        log.commit().unwrap();
+
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+        assert_eq!(Some(&b"committed"[..]), reader.next_record().unwrap());
+        assert_eq!(Some(&b"appended"[..]), reader.next_record().unwrap());
+        assert_eq!(None, reader.next_record().unwrap());
+    }
+
+    #[test]
+    fn reopen_unsealed() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        {
+            let mut log = unsafe { MmapLog::open(tempdir.path(), 128).unwrap() };
+            log.append(&[1; 40]).unwrap();
+            log.append(&[2; 40]).unwrap();
+            log.commit().unwrap();
+            assert_eq!(1, log.segment());
+        }
+
+        // Simulate a crash while rolling over, after the next segment was created but before the
+        // previous one was sealed.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(segment_path(tempdir.path(), 0))
+            .unwrap();
+        file.seek(SeekFrom::Start(SEALED_OFFSET as u64)).unwrap();
+        file.write_all(&[0; 8]).unwrap();
+
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+        assert_eq!(Some(&[1; 40][..]), reader.next_record().unwrap());
+        assert_eq!(None, reader.next_record().unwrap());
+
+        // Reopening the log seals the previous segment, so readers move on.
+        let _log = unsafe { MmapLog::open(tempdir.path(), 128).unwrap() };
+        assert_eq!(Some(&[2; 40][..]), reader.next_record().unwrap());
+        assert_eq!(None, reader.next_record().unwrap());
+    }
+
+    #[test]
+    fn torn_record() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let mut log = unsafe { MmapLog::open(tempdir.path(), 4096).unwrap() };
+        log.append(b"abc").unwrap();
+        log.commit().unwrap();
+
+        // A tail which ends inside a length prefix is reported as corrupt.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(segment_path(tempdir.path(), 0))
+            .unwrap();
+        file.seek(SeekFrom::Start(TAIL_OFFSET as u64)).unwrap();
+        file.write_all(&(HEADER_LEN as u64 + 2).to_le_bytes())
+            .unwrap();
+
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+        let error = reader.next_record().unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+    }
+
+    #[test]
+    fn segment_too_short() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        assert!(unsafe { MmapLog::open(tempdir.path(), 68) }.is_err());
+    }
+
+    #[test]
+    fn read_empty() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let mut reader = unsafe { MmapLogReader::open(tempdir.path()).unwrap() };
+        assert_eq!(None, reader.next_record().unwrap());
+
+        let mut log = unsafe { MmapLog::open(tempdir.path(), 4096).unwrap() };
+        log.append(b"abc").unwrap();
+        log.commit().unwrap();
+        assert_eq!(Some(&b"abc"[..]), reader.next_record().unwrap());
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Result;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use unix;
+use MmapMut;
+
+/// A mutable memory map backed by an anonymous memory file created with `memfd_create`.
+///
+/// Unlike an anonymous memory map, the memory of a `Memfd` can be mapped by other processes:
+/// the file descriptor returned by [`Memfd::as_file()`] may be inherited by child processes or
+/// sent to a peer over a Unix domain socket, and mapped again with [`MmapOptions`]. The memory
+/// is released when the last mapping of it is unmapped and the last file descriptor referring to
+/// it is closed.
+///
+/// Use [`MmapOptions::map_memfd()`] to create a `Memfd`.
+///
+/// Only supported on Linux.
+///
+/// ## Example
+///
+/// ```
+/// use memmap::MmapOptions;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut memfd = MmapOptions::new().len(13).map_memfd("example")?;
+/// memfd.copy_from_slice(b"Hello, world!");
+///
+/// // Map the same memory a second time, through the memfd's file descriptor.
+/// let mmap = unsafe { MmapOptions::new().map(memfd.as_file())? };
+/// assert_eq!(b"Hello, world!", &mmap[..]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MmapOptions`]: crate::MmapOptions
+/// [`MmapOptions::map_memfd()`]: crate::MmapOptions::map_memfd()
+pub struct Memfd {
+    file: File,
+    mmap: MmapMut,
+}
+
+impl Memfd {
+    pub(crate) fn new(file: File, mmap: MmapMut) -> Memfd {
+        Memfd { file, mmap }
+    }
+
+    /// Returns the memory file backing the memory map.
+    ///
+    /// Unless the memory map was configured with [`MmapOptions::inheritable()`], the file is
+    /// created with the `MFD_CLOEXEC` flag, so it is not inherited across `exec`.
+    ///
+    /// [`MmapOptions::inheritable()`]: crate::MmapOptions::inheritable()
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Splits the `Memfd` into the memory file and the memory map.
+    pub fn into_parts(self) -> (File, MmapMut) {
+        (self.file, self.mmap)
+    }
+
+    /// Unmaps the memory map and seals the memory file, making its contents immutable.
+    ///
+    /// The memory file is sealed with `F_SEAL_WRITE`, `F_SEAL_SHRINK` and `F_SEAL_GROW`, after
+    /// which no process can modify, shrink or grow it. The sealed file can be mapped without
+    /// `unsafe` using [`MmapOptions::map_sealed()`], in this process or by a peer which received
+    /// the file descriptor.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system call fails, for instance when
+    /// another writeable shared mapping of the memory file exists.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut memfd = MmapOptions::new().len(13).map_memfd("blob")?;
+    /// memfd.copy_from_slice(b"Hello, world!");
+    ///
+    /// let file = memfd.seal()?;
+    /// let mmap = MmapOptions::new().map_sealed(&file)?;
+    /// assert_eq!(b"Hello, world!", &mmap[..]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`MmapOptions::map_sealed()`]: crate::MmapOptions::map_sealed()
+    pub fn seal(self) -> Result<File> {
+        let (file, mmap) = self.into_parts();
+        // Sealing against writes fails while writeable shared mappings exist.
+        drop(mmap);
+        unix::seal(&file)?;
+        Ok(file)
+    }
+}
+
+impl Deref for Memfd {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+impl DerefMut for Memfd {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.mmap
+    }
+}
+
+impl AsRef<[u8]> for Memfd {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.deref()
+    }
+}
+
+impl AsMut<[u8]> for Memfd {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.deref_mut()
+    }
+}
+
+impl AsRawFd for Memfd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for Memfd {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Memfd")
+            .field("fd", &self.file.as_raw_fd())
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    use libc;
+
+    use super::Memfd;
+    use test::{assert_hugetlb_pool_empty, free_huge_pages};
+    use {HugePageSize, MmapOptions};
+
+    #[test]
+    fn map_memfd() {
+        let mut memfd = MmapOptions::new().len(128).map_memfd("memfd").unwrap();
+        assert_eq!(128, memfd.len());
+        assert_eq!(128, memfd.as_file().metadata().unwrap().len());
+
+        memfd[..6].copy_from_slice(b"abc123");
+
+        // The memory is visible through a second mapping of the file.
+        let mmap = unsafe { MmapOptions::new().map(memfd.as_file()).unwrap() };
+        assert_eq!(b"abc123", &mmap[..6]);
+
+        // ... and through the file itself.
+        let (mut file, mmap) = memfd.into_parts();
+        drop(mmap);
+        let mut read = [0u8; 6];
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_exact(&mut read).unwrap();
+        assert_eq!(b"abc123", &read);
+    }
+
+    #[test]
+    fn map_memfd_inheritable() {
+        let cloexec = |memfd: &Memfd| {
+            let flags = unsafe { libc::fcntl(memfd.as_file().as_raw_fd(), libc::F_GETFD) };
+            flags & libc::FD_CLOEXEC != 0
+        };
+        let memfd = MmapOptions::new().len(128).map_memfd("memfd").unwrap();
+        assert!(cloexec(&memfd));
+        let memfd = MmapOptions::new()
+            .len(128)
+            .inheritable()
+            .map_memfd("memfd")
+            .unwrap();
+        assert!(!cloexec(&memfd));
+    }
+
+    #[test]
+    fn seal() {
+        let mut memfd = MmapOptions::new().len(128).map_memfd("memfd").unwrap();
+        memfd[..6].copy_from_slice(b"abc123");
+
+        let mut file = memfd.seal().unwrap();
+        let mmap = MmapOptions::new().map_sealed(&file).unwrap();
+        assert_eq!(b"abc123", &mmap[..6]);
+        assert_eq!(128, mmap.len());
+
+        // The sealed file can not be modified, resized or mapped writeable.
+        assert!(file.write_all(b"xyz").is_err());
+        assert!(file.set_len(64).is_err());
+        assert!(file.set_len(256).is_err());
+        assert!(unsafe { MmapOptions::new().map_mut(&file) }.is_err());
+
+        // The memory map can not extend past the end of the sealed file.
+        assert!(MmapOptions::new().len(256).map_sealed(&file).is_err());
+        assert!(MmapOptions::new().offset(4096).map_sealed(&file).is_err());
+        let mmap = MmapOptions::new()
+            .offset(3)
+            .len(3)
+            .map_sealed(&file)
+            .unwrap();
+        assert_eq!(b"123", &mmap[..]);
+    }
+
+    #[test]
+    fn map_sealed_read_only() {
+        let mut file = unsafe {
+            let name = b"memfd\0";
+            let fd = libc::memfd_create(
+                name.as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            );
+            assert!(fd >= 0);
+            File::from_raw_fd(fd)
+        };
+        file.write_all(b"abc123").unwrap();
+        let seals = libc::F_SEAL_WRITE | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW;
+        assert_eq!(0, unsafe {
+            libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals)
+        });
+
+        // The file is open for reading and writing, but is mapped private and read-only.
+        let mmap = MmapOptions::new().map_sealed(&file).unwrap();
+        assert_eq!(b"abc123", &mmap[..]);
+        let mut maps = String::new();
+        File::open("/proc/self/maps")
+            .unwrap()
+            .read_to_string(&mut maps)
+            .unwrap();
+        let start = format!("{:x}-", mmap.as_ptr() as usize);
+        let line = maps.lines().find(|line| line.starts_with(&start)).unwrap();
+        assert_eq!(Some("r--p"), line.split_whitespace().nth(1));
+    }
+
+    #[test]
+    fn map_sealed_unsealed() {
+        let memfd = MmapOptions::new().len(128).map_memfd("memfd").unwrap();
+        assert!(MmapOptions::new().map_sealed(memfd.as_file()).is_err());
+    }
+
+    #[test]
+    fn map_memfd_invalid_name() {
+        assert!(MmapOptions::new().len(128).map_memfd("mem\0fd").is_err());
+    }
+
+    #[test]
+    fn map_memfd_zero_len() {
+        assert!(MmapOptions::new().map_memfd("memfd").is_err());
+    }
+
+    #[test]
+    fn map_memfd_huge_pages() {
+        let memfd = MmapOptions::new()
+            .len(128)
+            .huge_pages(HugePageSize::Size2MiB)
+            .map_memfd("memfd");
+        match free_huge_pages(2 << 20) {
+            Some(0) => assert_hugetlb_pool_empty(&memfd.unwrap_err()),
+            Some(_) => assert_eq!(2 << 20, memfd.unwrap().len()),
+            None => assert!(memfd.is_err()),
+        }
+    }
+}
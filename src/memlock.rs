@@ -0,0 +1,191 @@
+use std::io::Result;
+
+use {Mmap, MmapMut};
+
+#[cfg(unix)]
+use unix;
+
+macro_rules! impl_memory_locking {
+    ($map:ident) => {
+        impl $map {
+            /// Locks the memory map into RAM, so that accessing it never page faults.
+            ///
+            /// All pages of the memory map are faulted in before this method returns, and stay
+            /// resident until they are unlocked with [`unlock()`], or the memory map is dropped.
+            /// This is unrelated to the advisory file lock configured with
+            /// [`MmapOptions::lock()`](crate::MmapOptions::lock()).
+            ///
+            /// The amount of memory which a process may lock is limited by `RLIMIT_MEMLOCK`,
+            /// unless it has the `CAP_IPC_LOCK` capability. Only supported on Unix.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the underlying system call fails. When the
+            /// failure is due to `RLIMIT_MEMLOCK`, the error message reports the limit.
+            ///
+            /// [`unlock()`]: Self::unlock()
+            pub fn lock(&self) -> Result<()> {
+                self.inner.mlock(0, self.inner.len(), false)
+            }
+
+            /// Locks the memory map into RAM as its pages are faulted in.
+            ///
+            /// Unlike [`lock()`], pages which have not been accessed yet are not populated, and
+            /// are locked when they are first accessed (`MLOCK_ONFAULT`). This avoids committing
+            /// memory for large, sparsely used memory maps.
+            ///
+            /// Only supported on Linux 4.4 and newer.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the underlying system call fails.
+            ///
+            /// [`lock()`]: Self::lock()
+            pub fn lock_on_fault(&self) -> Result<()> {
+                self.inner.mlock(0, self.inner.len(), true)
+            }
+
+            /// Locks a range of the memory map into RAM.
+            ///
+            /// The range is extended to page boundaries. See [`lock()`] for details.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the range is out of the bounds of the memory
+            /// map, or when the underlying system call fails.
+            ///
+            /// [`lock()`]: Self::lock()
+            pub fn lock_range(&self, offset: usize, len: usize) -> Result<()> {
+                self.inner.mlock(offset, len, false)
+            }
+
+            /// Unlocks the memory map, allowing its pages to be swapped out again.
+            ///
+            /// Locks do not nest: a single unlock releases any number of locks of a page.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the underlying system call fails.
+            pub fn unlock(&self) -> Result<()> {
+                self.inner.munlock(0, self.inner.len())
+            }
+
+            /// Unlocks a range of the memory map.
+            ///
+            /// The range is extended to page boundaries.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the range is out of the bounds of the memory
+            /// map, or when the underlying system call fails.
+            pub fn unlock_range(&self, offset: usize, len: usize) -> Result<()> {
+                self.inner.munlock(offset, len)
+            }
+        }
+    };
+}
+
+impl_memory_locking!(Mmap);
+impl_memory_locking!(MmapMut);
+
+/// Locks all memory currently mapped by the process into RAM, with `mlockall`.
+///
+/// If `future` is set, memory which is mapped later, including the stack and heap of new threads,
+/// is locked as it is mapped (`MCL_FUTURE`). Note that a mapping which can not be locked then
+/// fails to be created once `RLIMIT_MEMLOCK` is reached.
+///
+/// Only supported on Unix.
+///
+/// # Errors
+///
+/// This function returns an error when the underlying system call fails. When the failure is due
+/// to `RLIMIT_MEMLOCK`, the error message reports the limit.
+#[cfg(unix)]
+pub fn lock_all(future: bool) -> Result<()> {
+    unix::mlockall(future)
+}
+
+/// Unlocks all memory mapped by the process, with `munlockall`.
+///
+/// This also cancels the effect of locking future mappings with [`lock_all()`].
+///
+/// Only supported on Unix.
+///
+/// # Errors
+///
+/// This function returns an error when the underlying system call fails.
+#[cfg(unix)]
+pub fn unlock_all() -> Result<()> {
+    unix::munlockall()
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    #[cfg(unix)]
+    use std::fs::OpenOptions;
+    #[cfg(unix)]
+    use std::io::ErrorKind;
+
+    #[cfg(unix)]
+    use MmapOptions;
+
+    #[test]
+    #[cfg(unix)]
+    fn lock() {
+        let mut mmap = MmapOptions::new().len(8192).map_anon().unwrap();
+        mmap.lock().unwrap();
+        mmap[..4].copy_from_slice(b"lock");
+        mmap.lock_range(4096, 1).unwrap();
+        mmap.unlock_range(4096, 1).unwrap();
+        mmap.unlock().unwrap();
+
+        let mmap = mmap.make_read_only().unwrap();
+        mmap.lock().unwrap();
+        assert_eq!(b"lock", &mmap[..4]);
+        mmap.unlock().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn lock_range_out_of_bounds() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let error = mmap.lock_range(4000, 100).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = mmap.unlock_range(usize::MAX, 2).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn lock_on_fault() {
+        let mut mmap = MmapOptions::new().len(8192).map_anon().unwrap();
+        mmap.lock_on_fault().unwrap();
+        mmap[4096] = 1;
+        mmap.unlock().unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn map_locked() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tempdir.path().join("mmap"))
+            .unwrap();
+        file.set_len(4096).unwrap();
+
+        let mut mmap = unsafe { MmapOptions::new().locked().map_mut(&file).unwrap() };
+        mmap[0] = 1;
+        let mmap = unsafe { MmapOptions::new().locked().map(&file).unwrap() };
+        assert_eq!(1, mmap[0]);
+
+        let mut mmap = MmapOptions::new().len(4096).locked().map_anon().unwrap();
+        mmap[0] = 1;
+        mmap.unlock().unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::Result;
+
+#[cfg(unix)]
+use unix as os;
+#[cfg(windows)]
+use windows as os;
+
+/// Returns the size of a page of memory, in bytes.
+///
+/// The value is queried from the operating system on the first call, and cached.
+///
+/// # Example
+///
+/// ```
+/// assert!(memmap::page_size().is_power_of_two());
+/// ```
+pub fn page_size() -> usize {
+    os::page_size()
+}
+
+/// Returns the granularity of memory map offsets, in bytes.
+///
+/// The offset configured with [`MmapOptions::offset()`](crate::MmapOptions::offset()) is
+/// mapped from the preceding multiple of the allocation granularity. On Unix, the allocation
+/// granularity is the page size; on Windows, it is usually 64 KiB.
+///
+/// The value is queried from the operating system on the first call, and cached.
+pub fn allocation_granularity() -> usize {
+    os::allocation_granularity()
+}
+
+/// Returns the sizes of the huge pages supported by the system, in bytes and in ascending order.
+///
+/// On Linux, the sizes are read from `/sys/kernel/mm/hugepages`; whether huge pages of a given
+/// size are available depends on the pages reserved in its pool. On Windows, the large page size
+/// is returned, if large pages are supported. On other platforms, the list is empty.
+///
+/// # Errors
+///
+/// This function returns an error when the list of huge page sizes can not be read.
+pub fn huge_page_sizes() -> Result<Vec<usize>> {
+    os::huge_page_sizes()
+}
+
+/// Aligns the start of a range down to the allocation granularity.
+///
+/// Returns the offset rounded down to a multiple of [`allocation_granularity()`], and the length
+/// extended to keep the end of the range in place, so that mapping the returned range covers the
+/// given one. The given range starts `offset - aligned_offset` bytes into the returned range.
+///
+/// Returns `None` if the extended length overflows `usize`.
+///
+/// # Example
+///
+/// ```
+/// let granularity = memmap::allocation_granularity() as u64;
+/// let (offset, len) = memmap::align_down(granularity + 10, 20).unwrap();
+/// assert_eq!((granularity, 30), (offset, len));
+/// ```
+pub fn align_down(offset: u64, len: usize) -> Option<(u64, usize)> {
+    let alignment = offset % allocation_granularity() as u64;
+    let len = len.checked_add(alignment as usize)?;
+    Some((offset - alignment, len))
+}
+
+/// Aligns the start of a range up to the allocation granularity.
+///
+/// Returns the offset rounded up to a multiple of [`allocation_granularity()`], and the length
+/// shortened to keep the end of the range in place, so that the returned range is contained in
+/// the given one. The returned length is zero if the given range contains no aligned offset.
+///
+/// Returns `None` if the rounded up offset overflows `u64`.
+///
+/// # Example
+///
+/// ```
+/// let granularity = memmap::allocation_granularity();
+/// let (offset, len) = memmap::align_up(10, granularity).unwrap();
+/// assert_eq!((granularity as u64, 10), (offset, len));
+/// ```
+pub fn align_up(offset: u64, len: usize) -> Option<(u64, usize)> {
+    let granularity = allocation_granularity() as u64;
+    let aligned = offset.checked_add(granularity - 1)? / granularity * granularity;
+    // The offset moves by less than the granularity, which fits in a `usize`.
+    Some((aligned, len.saturating_sub((aligned - offset) as usize)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{align_down, align_up, allocation_granularity, huge_page_sizes, page_size};
+
+    #[test]
+    fn page_size_is_granular() {
+        assert!(page_size().is_power_of_two());
+        assert_eq!(0, allocation_granularity() % page_size());
+        assert!(huge_page_sizes()
+            .unwrap()
+            .iter()
+            .all(|size| size % page_size() == 0));
+    }
+
+    #[test]
+    fn align() {
+        let granularity = allocation_granularity();
+        let g = granularity as u64;
+
+        assert_eq!(Some((0, 0)), align_down(0, 0));
+        assert_eq!(Some((g, 10)), align_down(g, 10));
+        assert_eq!(Some((g, granularity)), align_down(2 * g - 1, 1));
+        assert_eq!(Some((g, usize::MAX)), align_down(g, usize::MAX));
+        assert_eq!(None, align_down(g + 1, usize::MAX));
+
+        assert_eq!(Some((0, 10)), align_up(0, 10));
+        assert_eq!(Some((g, 0)), align_up(1, 10));
+        assert_eq!(Some((2 * g, 1)), align_up(g + 1, granularity));
+        let last = u64::MAX / g * g;
+        assert_eq!(Some((last, 10)), align_up(last, 10));
+        assert_eq!(None, align_up(last + 1, 10));
+    }
+}
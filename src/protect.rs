@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Range, RangeBounds};
+
+use {page_size, range_bounds, Mmap, MmapMut, MmapRaw, Protection};
+
+/// The protection of the pages of a memory map, tracked as runs of equal protection keyed by
+/// their start offset in the memory map.
+pub struct ProtectionMap {
+    runs: BTreeMap<usize, Protection>,
+}
+
+impl ProtectionMap {
+    /// Creates a protection map with a single protection for the whole memory map.
+    pub fn new(protection: Protection) -> ProtectionMap {
+        let mut runs = BTreeMap::new();
+        runs.insert(0, protection);
+        ProtectionMap { runs }
+    }
+
+    /// Returns the protection at `offset`.
+    fn get(&self, offset: usize) -> Protection {
+        *self.runs.range(..=offset).next_back().unwrap().1
+    }
+
+    /// Sets the protection of `start..end`, in a memory map of length `len`.
+    pub fn set(&mut self, start: usize, end: usize, len: usize, protection: Protection) {
+        let after = self.get(end);
+        let covered: Vec<usize> = self.runs.range(start..=end).map(|(&key, _)| key).collect();
+        for key in covered {
+            self.runs.remove(&key);
+        }
+        if start == 0 || self.get(start - 1) != protection {
+            self.runs.insert(start, protection);
+        }
+        if end < len && after != protection {
+            self.runs.insert(end, after);
+        }
+    }
+
+    /// Forgets the protection of pages past a new length of the memory map.
+    #[cfg(target_os = "linux")]
+    pub fn truncate(&mut self, len: usize) {
+        self.runs.split_off(&len);
+    }
+
+    /// Returns the runs of equal protection in a memory map of length `len`.
+    pub fn runs(&self, len: usize) -> Vec<(Range<usize>, Protection)> {
+        let mut runs: Vec<(Range<usize>, Protection)> = Vec::new();
+        for (&start, &protection) in self.runs.range(..len) {
+            if let Some(last) = runs.last_mut() {
+                last.0.end = start;
+            }
+            runs.push((start..len, protection));
+        }
+        runs
+    }
+}
+
+/// Checks that a range of a memory map starting at `ptr` is in bounds, and that its ends are page
+/// boundaries or the ends of the memory map.
+pub fn check_range(ptr: *const u8, map_len: usize, offset: usize, len: usize) -> Result<()> {
+    let end = match offset.checked_add(len) {
+        Some(end) if end <= map_len => end,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "range is out of the bounds of the memory map",
+            ))
+        }
+    };
+    let aligned = |offset: usize| {
+        offset == 0 || offset == map_len || (ptr as usize + offset) % page_size() == 0
+    };
+    if aligned(offset) && aligned(end) {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "range is not page-aligned",
+        ))
+    }
+}
+
+macro_rules! impl_protection {
+    ($map:ident) => {
+        impl $map {
+            /// Changes the protection of a range of the memory map.
+            ///
+            /// The ends of the range must be page boundaries, or the ends of the memory map; the
+            /// first and last pages of a memory map which does not start or end on a page boundary
+            /// are protected as a whole. [`Protection::None`] makes the range inaccessible, for
+            /// instance to set up guard pages.
+            ///
+            /// The protection of each range is tracked, and can be queried with
+            /// [`protection()`](Self::protection()).
+            ///
+            /// # Safety
+            ///
+            /// The range must not be accessed in a way its new protection does not allow,
+            /// including through references to the memory map's contents, for as long as it
+            /// has that protection. Such accesses raise `SIGSEGV` on Unix, or an access violation
+            /// on Windows.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the range is out of the bounds of the memory map
+            /// or not page-aligned, or when the underlying system call fails, which can happen
+            /// when the protection is not permitted by the file the memory map is backed by.
+            pub unsafe fn protect_range<R: RangeBounds<usize>>(
+                &mut self,
+                range: R,
+                protection: Protection,
+            ) -> Result<()> {
+                let (offset, len) = range_bounds(range, self.inner.len())?;
+                check_range(self.inner.ptr(), self.inner.len(), offset, len)?;
+                if len == 0 {
+                    return Ok(());
+                }
+                self.inner.protect(offset, len, protection)
+            }
+
+            /// Returns the protection of the memory map, as ranges of equal protection in
+            /// ascending order.
+            pub fn protection(&self) -> Vec<(Range<usize>, Protection)> {
+                self.inner.protection()
+            }
+        }
+    };
+}
+
+impl_protection!(Mmap);
+impl_protection!(MmapMut);
+impl_protection!(MmapRaw);
+
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+
+    use super::ProtectionMap;
+    use {MmapOptions, Protection};
+
+    #[test]
+    fn protection_map() {
+        let mut map = ProtectionMap::new(Protection::ReadWrite);
+        assert_eq!(vec![(0..100, Protection::ReadWrite)], map.runs(100));
+
+        map.set(10, 20, 100, Protection::None);
+        map.set(30, 40, 100, Protection::Read);
+        assert_eq!(
+            vec![
+                (0..10, Protection::ReadWrite),
+                (10..20, Protection::None),
+                (20..30, Protection::ReadWrite),
+                (30..40, Protection::Read),
+                (40..100, Protection::ReadWrite),
+            ],
+            map.runs(100)
+        );
+
+        map.set(15, 35, 100, Protection::ReadWrite);
+        assert_eq!(
+            vec![
+                (0..10, Protection::ReadWrite),
+                (10..15, Protection::None),
+                (15..35, Protection::ReadWrite),
+                (35..40, Protection::Read),
+                (40..100, Protection::ReadWrite),
+            ],
+            map.runs(100)
+        );
+
+        map.set(0, 15, 100, Protection::ReadWrite);
+        map.set(35, 100, 100, Protection::ReadWrite);
+        assert_eq!(vec![(0..100, Protection::ReadWrite)], map.runs(100));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn protection_map_truncate() {
+        let mut map = ProtectionMap::new(Protection::ReadWrite);
+        map.set(50, 100, 100, Protection::Read);
+        map.truncate(50);
+        assert_eq!(vec![(0..50, Protection::ReadWrite)], map.runs(50));
+        // Pages added when the memory map grows take the protection of its last pages.
+        assert_eq!(vec![(0..60, Protection::ReadWrite)], map.runs(60));
+    }
+
+    #[test]
+    fn protect_range() {
+        let mut mmap = MmapOptions::new().len(3 * 4096).map_anon().unwrap();
+        mmap[0] = 1;
+        unsafe {
+            mmap.protect_range(4096..8192, Protection::None).unwrap();
+            mmap.protect_range(8192.., Protection::Read).unwrap();
+        }
+        assert_eq!(
+            vec![
+                (0..4096, Protection::ReadWrite),
+                (4096..8192, Protection::None),
+                (8192..3 * 4096, Protection::Read),
+            ],
+            mmap.protection()
+        );
+        assert_eq!(1, mmap[0]);
+        assert_eq!(0, mmap[8192]);
+
+        let error = unsafe { mmap.protect_range(100..4096, Protection::Read).unwrap_err() };
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = unsafe { mmap.protect_range(0..4097, Protection::Read).unwrap_err() };
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = unsafe {
+            mmap.protect_range(0..4 * 4096, Protection::Read)
+                .unwrap_err()
+        };
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+
+        unsafe { mmap.protect_range(.., Protection::ReadWrite).unwrap() };
+        assert_eq!(
+            vec![(0..3 * 4096, Protection::ReadWrite)],
+            mmap.protection()
+        );
+        mmap[4096] = 1;
+
+        let mmap = mmap.make_read_only().unwrap();
+        assert_eq!(vec![(0..3 * 4096, Protection::Read)], mmap.protection());
+    }
+}
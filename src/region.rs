@@ -0,0 +1,326 @@
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::{fmt, slice};
+
+use {MmapInner, MmapMut};
+
+impl MmapMut {
+    /// Splits the memory map into two owned, disjoint mutable regions at `mid`.
+    ///
+    /// The first region covers `[0, mid)`, and the second `[mid, len)`. The regions share the
+    /// memory map through reference counting, so it stays alive until the last region is
+    /// dropped.
+    ///
+    /// To split the memory map into borrowed slices instead, split its slice:
+    /// `mmap[..].split_at_mut(mid)`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `mid` is greater than the length of the memory map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memmap::MmapOptions;
+    /// use std::thread;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mmap = MmapOptions::new().len(8192).map_anon()?;
+    /// let (mut first, second) = mmap.split_at(4096);
+    ///
+    /// let handle = thread::spawn(move || {
+    ///     let mut second = second;
+    ///     second.fill(2);
+    ///     second
+    /// });
+    /// first.fill(1);
+    ///
+    /// let second = handle.join().unwrap();
+    /// assert!(first.iter().all(|&byte| byte == 1));
+    /// assert!(second.iter().all(|&byte| byte == 2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split_at(self, mid: usize) -> (MmapMutRegion, MmapMutRegion) {
+        MmapMutRegion::from(self).split_at(mid)
+    }
+
+    /// Splits the memory map into `n` owned, disjoint mutable regions of nearly equal length.
+    ///
+    /// The regions are contiguous and in order, and their lengths differ by at most one byte.
+    /// The regions share the memory map through reference counting, so it stays alive until the
+    /// last region is dropped.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `n` is zero.
+    pub fn split_into(self, n: usize) -> Vec<MmapMutRegion> {
+        MmapMutRegion::from(self).split_into(n)
+    }
+}
+
+/// An owned mutable region of a memory map.
+///
+/// Regions are created by splitting a [`MmapMut`] with [`MmapMut::split_at()`] or
+/// [`MmapMut::split_into()`]. Regions of the same memory map are disjoint, so they can be
+/// written concurrently from different threads. The memory map stays alive until the last
+/// region is dropped.
+pub struct MmapMutRegion {
+    inner: Arc<MmapInner>,
+    offset: usize,
+    len: usize,
+}
+
+impl MmapMutRegion {
+    /// Returns the length of the region in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the region is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the offset of the region in the memory map.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Splits the region into two owned, disjoint mutable regions at `mid`.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `mid` is greater than the length of the region.
+    pub fn split_at(self, mid: usize) -> (MmapMutRegion, MmapMutRegion) {
+        assert!(
+            mid <= self.len,
+            "split offset {} is out of the bounds of the region of {} bytes",
+            mid,
+            self.len
+        );
+        let second = MmapMutRegion {
+            inner: self.inner.clone(),
+            offset: self.offset + mid,
+            len: self.len - mid,
+        };
+        let first = MmapMutRegion {
+            inner: self.inner,
+            offset: self.offset,
+            len: mid,
+        };
+        (first, second)
+    }
+
+    /// Splits the region into `n` owned, disjoint mutable regions of nearly equal length.
+    ///
+    /// The regions are contiguous and in order, and their lengths differ by at most one byte.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `n` is zero.
+    pub fn split_into(self, n: usize) -> Vec<MmapMutRegion> {
+        assert!(n > 0, "a region can not be split into zero regions");
+        let (len, remainder) = (self.len / n, self.len % n);
+        let mut offset = self.offset;
+        (0..n)
+            .map(|i| {
+                let len = len + usize::from(i < remainder);
+                let region = MmapMutRegion {
+                    inner: self.inner.clone(),
+                    offset,
+                    len,
+                };
+                offset += len;
+                region
+            })
+            .collect()
+    }
+
+    /// Flushes outstanding modifications to the region to disk.
+    ///
+    /// See [`MmapMut::flush()`].
+    pub fn flush(&self) -> Result<()> {
+        self.inner.flush(self.offset, self.len)
+    }
+
+    /// Asynchronously flushes outstanding modifications to the region to disk.
+    ///
+    /// See [`MmapMut::flush_async()`].
+    pub fn flush_async(&self) -> Result<()> {
+        self.inner.flush_async(self.offset, self.len)
+    }
+
+    /// Flushes outstanding modifications to a range of the region to disk.
+    ///
+    /// The offset is relative to the start of the region. Returns an error of kind
+    /// `InvalidInput` when the range is out of the bounds of the region.
+    pub fn flush_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.check_range(offset, len)?;
+        self.inner.flush(self.offset + offset, len)
+    }
+
+    /// Asynchronously flushes outstanding modifications to a range of the region to disk.
+    ///
+    /// The offset is relative to the start of the region. Returns an error of kind
+    /// `InvalidInput` when the range is out of the bounds of the region.
+    pub fn flush_async_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.check_range(offset, len)?;
+        self.inner.flush_async(self.offset + offset, len)
+    }
+
+    fn check_range(&self, offset: usize, len: usize) -> Result<()> {
+        if offset.checked_add(len).map_or(false, |end| end <= self.len) {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "range of {} bytes at offset {} is out of the bounds of the region of {} bytes",
+                    len, offset, self.len
+                ),
+            ))
+        }
+    }
+}
+
+impl From<MmapMut> for MmapMutRegion {
+    fn from(mmap: MmapMut) -> MmapMutRegion {
+        MmapMutRegion {
+            len: mmap.inner.len(),
+            inner: Arc::new(mmap.inner),
+            offset: 0,
+        }
+    }
+}
+
+impl Deref for MmapMutRegion {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.inner.ptr().add(self.offset), self.len) }
+    }
+}
+
+impl DerefMut for MmapMutRegion {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // Regions of a memory map are disjoint, so this is the only reference to the bytes.
+        let ptr = self.inner.ptr() as *mut u8;
+        unsafe { slice::from_raw_parts_mut(ptr.add(self.offset), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for MmapMutRegion {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.deref()
+    }
+}
+
+impl AsMut<[u8]> for MmapMutRegion {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.deref_mut()
+    }
+}
+
+impl fmt::Debug for MmapMutRegion {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("MmapMutRegion")
+            .field("ptr", &self.as_ptr())
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    use std::fs::OpenOptions;
+    use std::io::{ErrorKind, Read};
+    use std::thread;
+
+    use MmapOptions;
+
+    #[test]
+    fn split_into() {
+        let mmap = MmapOptions::new().len(10).map_anon().unwrap();
+        let regions = mmap.split_into(4);
+        let lens: Vec<usize> = regions.iter().map(|region| region.len()).collect();
+        assert_eq!(vec![3, 3, 2, 2], lens);
+        let offsets: Vec<usize> = regions.iter().map(|region| region.offset()).collect();
+        assert_eq!(vec![0, 3, 6, 8], offsets);
+
+        let handles: Vec<_> = regions
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut region)| {
+                thread::spawn(move || {
+                    region.fill(i as u8);
+                    region
+                })
+            })
+            .collect();
+        let regions: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let (first, rest) = regions.split_first().unwrap();
+        assert_eq!(&[0, 0, 0], &first[..]);
+        assert_eq!(&[3, 3], &rest[2][..]);
+
+        let empty = MmapOptions::new().len(2).map_anon().unwrap().split_into(3);
+        assert_eq!(3, empty.len());
+        assert!(empty[2].is_empty());
+    }
+
+    #[test]
+    fn split_at_flush() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(16).unwrap();
+
+        let mmap = unsafe { MmapOptions::new().map_mut(&file).unwrap() };
+        let (first, mut second) = mmap.split_at(8);
+        drop(first);
+        second[..4].copy_from_slice(b"abcd");
+        second.flush().unwrap();
+        second.flush_range(2, 6).unwrap();
+
+        let (mut left, right) = second.split_at(4);
+        assert_eq!(12, right.offset());
+        left.fill(b'x');
+        left.flush_async_range(0, 4).unwrap();
+        drop(left);
+        right.flush().unwrap();
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(b"\0\0\0\0\0\0\0\0xxxx\0\0\0\0", &contents[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_at_out_of_bounds() {
+        MmapOptions::new().len(8).map_anon().unwrap().split_at(9);
+    }
+
+    #[test]
+    fn flush_range_out_of_bounds() {
+        let (first, _) = MmapOptions::new().len(8).map_anon().unwrap().split_at(4);
+        let err = first.flush_range(2, 3).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        let err = first.flush_async_range(usize::MAX, 2).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+    }
+}
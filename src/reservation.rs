@@ -0,0 +1,212 @@
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Range, RangeBounds};
+
+use protect::check_range;
+use {page_size, range_bounds, MmapInner, Protection};
+
+/// A reserved range of address space, which memory is committed to incrementally.
+///
+/// A reservation is an inaccessible (`PROT_NONE`) anonymous memory map, created with
+/// `MAP_NORESERVE` on Linux so that no memory or swap space is accounted for it. Ranges of the
+/// reservation are made readable and writeable with [`commit()`], and released again with
+/// [`decommit()`]. The reservation is never moved, so pointers into it stay valid until it is
+/// dropped; this allows reserving as much address space as a data structure may ever need, and
+/// growing it in place.
+///
+/// A `Reservation` only hands out raw pointers to its memory. Accessing a range which is not
+/// committed raises `SIGSEGV`.
+///
+/// Only supported on Unix.
+///
+/// # Example
+///
+/// ```
+/// use memmap::Reservation;
+///
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// #
+/// # #[cfg(unix)]
+/// # fn main() -> std::io::Result<()> {
+/// // Reserve 1 GiB of address space, and commit its first page.
+/// let mut reservation = Reservation::new(1 << 30)?;
+/// let page_size = memmap::page_size();
+/// reservation.commit(..page_size)?;
+///
+/// unsafe { reservation.as_mut_ptr().write(42) };
+/// assert_eq!(42, unsafe { reservation.as_ptr().read() });
+/// assert_eq!(vec![0..page_size], reservation.committed());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`commit()`]: Reservation::commit()
+/// [`decommit()`]: Reservation::decommit()
+pub struct Reservation {
+    inner: MmapInner,
+}
+
+impl Reservation {
+    /// Reserves `len` bytes of address space, rounded up to a multiple of the page size.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when `len` is 0, or when the underlying system call fails,
+    /// for instance because the address space is exhausted.
+    pub fn new(len: usize) -> Result<Reservation> {
+        let page_size = page_size();
+        let len = len
+            .checked_add(page_size - 1)
+            .map(|len| len / page_size * page_size)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "reservation length overflows"))?;
+        MmapInner::reserve(len).map(|inner| Reservation { inner })
+    }
+
+    /// Commits memory to a range of the reservation, making it readable and writeable.
+    ///
+    /// Newly committed memory is zeroed. Committing a range which is already committed has no
+    /// effect on its contents. The ends of the range must be page boundaries.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the reservation or
+    /// not page-aligned, or when the underlying system call fails.
+    pub fn commit<R: RangeBounds<usize>>(&mut self, range: R) -> Result<()> {
+        let (offset, len) = self.check(range)?;
+        if len == 0 {
+            return Ok(());
+        }
+        self.inner.protect(offset, len, Protection::ReadWrite)
+    }
+
+    /// Decommits a range of the reservation, releasing its memory and making it inaccessible.
+    ///
+    /// The memory is released with `madvise(MADV_DONTNEED)`, so on Linux, the range is zeroed
+    /// when it is committed again. The address space stays reserved. The ends of the range must
+    /// be page boundaries.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the range is out of the bounds of the reservation or
+    /// not page-aligned, or when the underlying system call fails.
+    pub fn decommit<R: RangeBounds<usize>>(&mut self, range: R) -> Result<()> {
+        let (offset, len) = self.check(range)?;
+        if len == 0 {
+            return Ok(());
+        }
+        self.inner.decommit(offset, len)
+    }
+
+    /// Returns the committed ranges of the reservation, in ascending order.
+    pub fn committed(&self) -> Vec<Range<usize>> {
+        self.inner
+            .protection()
+            .into_iter()
+            .filter(|&(_, protection)| protection == Protection::ReadWrite)
+            .map(|(range, _)| range)
+            .collect()
+    }
+
+    /// Returns a raw pointer to the start of the reservation.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.inner.ptr()
+    }
+
+    /// Returns a raw mutable pointer to the start of the reservation.
+    #[inline]
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.inner.ptr() as *mut u8
+    }
+
+    /// Returns the length of the reservation, in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the reservation is empty, which is never the case.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.len() == 0
+    }
+
+    /// Converts a range of the reservation to an offset and length, checking its bounds and
+    /// alignment.
+    fn check<R: RangeBounds<usize>>(&self, range: R) -> Result<(usize, usize)> {
+        let (offset, len) = range_bounds(range, self.inner.len())?;
+        check_range(self.inner.ptr(), self.inner.len(), offset, len)?;
+        Ok((offset, len))
+    }
+}
+
+impl fmt::Debug for Reservation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Reservation")
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(unix)]
+    use super::Reservation;
+    #[cfg(unix)]
+    use page_size;
+
+    #[test]
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    fn reservation() {
+        use std::io::ErrorKind;
+
+        let page_size = page_size();
+        let mut reservation = Reservation::new(64 << 30).unwrap();
+        assert_eq!(64 << 30, reservation.len());
+        assert!(reservation.committed().is_empty());
+        let ptr = reservation.as_mut_ptr();
+
+        reservation.commit(..2 * page_size).unwrap();
+        reservation.commit(4 * page_size..5 * page_size).unwrap();
+        assert_eq!(
+            vec![0..2 * page_size, 4 * page_size..5 * page_size],
+            reservation.committed()
+        );
+        unsafe {
+            ptr.write(1);
+            ptr.add(2 * page_size - 1).write(2);
+            ptr.add(4 * page_size).write(3);
+        }
+
+        reservation.decommit(page_size..2 * page_size).unwrap();
+        assert_eq!(
+            vec![0..page_size, 4 * page_size..5 * page_size],
+            reservation.committed()
+        );
+
+        // Decommitted memory is zeroed when it is committed again.
+        reservation.commit(..2 * page_size).unwrap();
+        assert_eq!(ptr, reservation.as_mut_ptr());
+        unsafe {
+            assert_eq!(1, ptr.read());
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            assert_eq!(0, ptr.add(2 * page_size - 1).read());
+            assert_eq!(3, ptr.add(4 * page_size).read());
+        }
+
+        let error = reservation.commit(1..page_size).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = reservation.decommit(..(64 << 30) + 1).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reservation_len() {
+        assert_eq!(page_size(), Reservation::new(1).unwrap().len());
+        assert!(Reservation::new(0).is_err());
+        assert!(Reservation::new(usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::Result;
+use std::ops::RangeBounds;
+
+use {range_bounds, Mmap, MmapMut};
+
+macro_rules! impl_residency {
+    ($map:ident) => {
+        impl $map {
+            /// Returns whether each page of the memory map is resident in memory.
+            ///
+            /// Entry `i` of the returned bitmap describes the `i`-th page overlapping the memory
+            /// map, starting with the page containing its first byte; for file-backed memory maps,
+            /// a resident page is in the page cache. The result is a snapshot, which may be out of
+            /// date by the time it is returned.
+            ///
+            /// Only supported on Unix, with `mincore`.
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the underlying system call fails.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// use memmap::MmapOptions;
+            ///
+            /// # #[cfg(not(unix))]
+            /// # fn main() {}
+            /// #
+            /// # #[cfg(unix)]
+            /// # fn main() -> std::io::Result<()> {
+            /// let mut mmap = MmapOptions::new().len(4096).map_anon()?;
+            /// mmap[0] = 1;
+            /// assert_eq!(vec![true], mmap.residency()?);
+            /// # Ok(())
+            /// # }
+            /// ```
+            pub fn residency(&self) -> Result<Vec<bool>> {
+                self.inner.mincore(0, self.inner.len())
+            }
+
+            /// Returns the number of bytes of the memory map which are resident in memory.
+            ///
+            /// See [`residency()`](Self::residency()).
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the underlying system call fails.
+            pub fn resident_bytes(&self) -> Result<usize> {
+                self.inner.resident_bytes()
+            }
+
+            /// Returns whether every page overlapping a range of the memory map is resident in
+            /// memory.
+            ///
+            /// An empty range is always resident. See [`residency()`](Self::residency()).
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the range is out of the bounds of the memory map,
+            /// or when the underlying system call fails.
+            pub fn is_resident<R: RangeBounds<usize>>(&self, range: R) -> Result<bool> {
+                let (offset, len) = range_bounds(range, self.inner.len())?;
+                if len == 0 {
+                    return Ok(true);
+                }
+                Ok(self
+                    .inner
+                    .mincore(offset, len)?
+                    .into_iter()
+                    .all(|page| page))
+            }
+        }
+    };
+}
+
+impl_residency!(Mmap);
+impl_residency!(MmapMut);
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    #[cfg(unix)]
+    use std::fs::OpenOptions;
+    #[cfg(unix)]
+    use std::io::ErrorKind;
+
+    #[cfg(unix)]
+    use {page_size, MmapOptions};
+
+    #[test]
+    #[cfg(unix)]
+    fn residency() {
+        let page = page_size();
+        let mut mmap = MmapOptions::new().len(3 * page).map_anon().unwrap();
+        assert_eq!(vec![false; 3], mmap.residency().unwrap());
+        assert_eq!(0, mmap.resident_bytes().unwrap());
+
+        mmap[page] = 1;
+        assert_eq!(vec![false, true, false], mmap.residency().unwrap());
+        assert_eq!(page, mmap.resident_bytes().unwrap());
+        assert!(mmap.is_resident(page..2 * page).unwrap());
+        assert!(mmap.is_resident(page + 1..=page + 1).unwrap());
+        assert!(!mmap.is_resident(page - 1..page + 1).unwrap());
+        assert!(!mmap.is_resident(..).unwrap());
+        assert!(mmap.is_resident(0..0).unwrap());
+
+        let error = mmap.is_resident(0..3 * page + 1).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = mmap.is_resident(..=usize::MAX).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn residency_offset() {
+        let page = page_size();
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tempdir.path().join("mmap"))
+            .unwrap();
+        file.set_len(3 * page as u64).unwrap();
+
+        // The memory map starts 100 bytes into its first page.
+        let mmap = unsafe { MmapOptions::new().offset(100).map_mut(&file).unwrap() };
+        mmap.lock().unwrap();
+        assert_eq!(vec![true; 3], mmap.residency().unwrap());
+        assert_eq!(3 * page - 100, mmap.resident_bytes().unwrap());
+        assert!(mmap.is_resident(..).unwrap());
+    }
+}
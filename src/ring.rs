@@ -0,0 +1,218 @@
+use std::io::Result;
+use std::{fmt, slice};
+
+use MmapInner;
+
+/// A ring buffer whose storage is mapped twice back-to-back.
+///
+/// The storage is an anonymous memory file (see `memfd_create(2)`), mapped into two adjacent
+/// halves of a reserved address range, so that the byte following the end of the buffer is the
+/// byte at its start. As a result, the readable and writable regions of the buffer are always
+/// contiguous slices, even when they wrap around the end of the buffer, and never need to be
+/// split.
+///
+/// Data is written into the slice returned by [`writable()`] and committed with [`produce()`];
+/// it is read from the slice returned by [`readable()`] and released with [`consume()`].
+///
+/// ## Example
+///
+/// ```
+/// use memmap::RingBuffer;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut ring = RingBuffer::new(4096)?;
+/// let capacity = ring.capacity();
+///
+/// // Fill the buffer, and release all but the last three bytes.
+/// ring.writable().fill(0);
+/// ring.produce(capacity);
+/// ring.consume(capacity - 3);
+///
+/// // The write wraps around the end of the buffer, but is still a single slice.
+/// ring.writable()[..6].copy_from_slice(b"abcdef");
+/// ring.produce(6);
+/// assert_eq!(b"\0\0\0abcdef", ring.readable());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`writable()`]: RingBuffer::writable()
+/// [`produce()`]: RingBuffer::produce()
+/// [`readable()`]: RingBuffer::readable()
+/// [`consume()`]: RingBuffer::consume()
+pub struct RingBuffer {
+    inner: MmapInner,
+    capacity: usize,
+    /// The offset of the first readable byte, always less than the capacity.
+    head: usize,
+    /// The number of readable bytes.
+    len: usize,
+}
+
+impl RingBuffer {
+    /// Creates an empty ring buffer.
+    ///
+    /// The capacity is rounded up to a multiple of the page size.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the capacity is zero, or when the underlying system
+    /// calls fail.
+    pub fn new(capacity: usize) -> Result<RingBuffer> {
+        let inner = MmapInner::map_ring(capacity)?;
+        Ok(RingBuffer {
+            capacity: inner.len() / 2,
+            inner,
+            head: 0,
+            len: 0,
+        })
+    }
+
+    /// Returns the capacity of the ring buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of readable bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no readable bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if there is no space to write to.
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+
+    /// Returns the readable bytes, in the order they were produced.
+    pub fn readable(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.inner.ptr().add(self.head), self.len) }
+    }
+
+    /// Returns the free space following the readable bytes.
+    ///
+    /// Bytes written to the slice become readable once they are committed with [`produce()`].
+    ///
+    /// [`produce()`]: RingBuffer::produce()
+    pub fn writable(&mut self) -> &mut [u8] {
+        let tail = self.head + self.len;
+        let free = self.capacity - self.len;
+        unsafe { slice::from_raw_parts_mut(self.inner.mut_ptr().add(tail), free) }
+    }
+
+    /// Makes the first `len` bytes of the [`writable()`] slice readable.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `len` exceeds the free space.
+    ///
+    /// [`writable()`]: RingBuffer::writable()
+    pub fn produce(&mut self, len: usize) {
+        assert!(
+            len <= self.capacity - self.len,
+            "produce length {} exceeds the free space {}",
+            len,
+            self.capacity - self.len
+        );
+        self.len += len;
+    }
+
+    /// Releases the first `len` bytes of the [`readable()`] slice, making space to write to.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when `len` exceeds the number of readable bytes.
+    ///
+    /// [`readable()`]: RingBuffer::readable()
+    pub fn consume(&mut self, len: usize) {
+        assert!(
+            len <= self.len,
+            "consume length {} exceeds the readable length {}",
+            len,
+            self.len
+        );
+        self.head = (self.head + len) % self.capacity;
+        self.len -= len;
+    }
+
+    /// Releases all readable bytes.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+impl fmt::Debug for RingBuffer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RingBuffer")
+            .field("ptr", &self.inner.ptr())
+            .field("capacity", &self.capacity)
+            .field("head", &self.head)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RingBuffer;
+
+    #[test]
+    fn capacity() {
+        let ring = RingBuffer::new(1).unwrap();
+        assert!(ring.capacity() > 1);
+        assert!(ring.is_empty());
+        assert_eq!(
+            ring.capacity(),
+            RingBuffer::new(ring.capacity()).unwrap().capacity()
+        );
+
+        assert!(RingBuffer::new(0).is_err());
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut ring = RingBuffer::new(1).unwrap();
+        let capacity = ring.capacity();
+
+        ring.produce(capacity - 2);
+        assert_eq!(2, ring.writable().len());
+        ring.consume(capacity - 2);
+        assert!(ring.is_empty());
+
+        // The writable slice wraps around the end of the buffer.
+        let data: Vec<u8> = (0..capacity).map(|i| i as u8).collect();
+        assert_eq!(capacity, ring.writable().len());
+        ring.writable().copy_from_slice(&data);
+        ring.produce(capacity);
+        assert!(ring.is_full());
+        assert_eq!(&data[..], ring.readable());
+
+        ring.consume(3);
+        assert_eq!(&data[3..], ring.readable());
+        ring.writable().copy_from_slice(b"xyz");
+        ring.produce(3);
+        assert_eq!(&data[3..], &ring.readable()[..capacity - 3]);
+        assert_eq!(b"xyz", &ring.readable()[capacity - 3..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn produce_overflow() {
+        let mut ring = RingBuffer::new(1).unwrap();
+        let capacity = ring.capacity();
+        ring.produce(capacity + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn consume_overflow() {
+        let mut ring = RingBuffer::new(1).unwrap();
+        ring.produce(1);
+        ring.consume(2);
+    }
+}
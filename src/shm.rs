@@ -0,0 +1,368 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Result;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use libc;
+use unix;
+use {Mmap, MmapMut, MmapOptions};
+
+/// Options for creating or opening a [`SharedMemory`] region.
+///
+/// By default, regions are created with permissions `0o600`, an existing region with the same
+/// name is opened rather than replaced, and the region's name is not removed when the
+/// `SharedMemory` handle is dropped.
+///
+/// # Example
+///
+/// ```
+/// use memmap::SharedMemoryOptions;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let name = format!("/memmap-doc-options-{}", std::process::id());
+/// let shm = SharedMemoryOptions::new()
+///     .mode(0o640)
+///     .exclusive(true)
+///     .unlink_on_drop(true)
+///     .create(&name, 4096)?;
+/// assert_eq!(4096, shm.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedMemoryOptions {
+    mode: u32,
+    exclusive: bool,
+    unlink_on_drop: bool,
+}
+
+impl SharedMemoryOptions {
+    /// Creates a new set of options for creating or opening a shared memory region.
+    pub fn new() -> SharedMemoryOptions {
+        SharedMemoryOptions {
+            mode: 0o600,
+            exclusive: false,
+            unlink_on_drop: false,
+        }
+    }
+
+    /// Sets the permissions of newly created regions.
+    ///
+    /// The permissions are subject to the process umask. By default, the mode is `0o600`.
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Configures [`create()`] to fail if a region with the same name already exists.
+    ///
+    /// This corresponds to the `O_EXCL` flag of `shm_open`. By default, an existing region is
+    /// opened instead.
+    ///
+    /// [`create()`]: SharedMemoryOptions::create()
+    pub fn exclusive(&mut self, exclusive: bool) -> &mut Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Configures the region's name to be removed when the `SharedMemory` handle is dropped.
+    ///
+    /// Memory maps of the region remain valid after its name is removed.
+    pub fn unlink_on_drop(&mut self, unlink_on_drop: bool) -> &mut Self {
+        self.unlink_on_drop = unlink_on_drop;
+        self
+    }
+
+    /// Creates a shared memory region of `len` bytes, or opens an existing region.
+    ///
+    /// When an existing region is opened, it is grown to `len` bytes if it is shorter; its
+    /// contents are preserved.
+    ///
+    /// The name should be of the form `/name`, starting with a slash and containing no other
+    /// slashes.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system calls fail, which can happen for
+    /// a variety of reasons, such as when the region already exists and the options are
+    /// [`exclusive()`], or when the region exists and the process lacks permission to open it.
+    ///
+    /// [`exclusive()`]: SharedMemoryOptions::exclusive()
+    pub fn create(&self, name: &str, len: usize) -> Result<SharedMemory> {
+        let exclusive = if self.exclusive { libc::O_EXCL } else { 0 };
+        let file = unix::shm_open(name, libc::O_RDWR | libc::O_CREAT | exclusive, self.mode)?;
+        if file.metadata()?.len() < len as u64 {
+            if let Err(error) = file.set_len(len as u64) {
+                if self.exclusive {
+                    let _ = unix::shm_unlink(name);
+                }
+                return Err(error);
+            }
+        }
+        self.finish(name, file)
+    }
+
+    /// Opens an existing shared memory region.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the underlying system calls fail, which can happen for
+    /// a variety of reasons, such as when no region with the name exists.
+    pub fn open(&self, name: &str) -> Result<SharedMemory> {
+        let file = unix::shm_open(name, libc::O_RDWR, 0)?;
+        self.finish(name, file)
+    }
+
+    fn finish(&self, name: &str, file: File) -> Result<SharedMemory> {
+        let len = file.metadata()?.len() as usize;
+        Ok(SharedMemory {
+            name: name.to_owned(),
+            file,
+            len,
+            unlink_on_drop: self.unlink_on_drop,
+        })
+    }
+}
+
+impl Default for SharedMemoryOptions {
+    fn default() -> SharedMemoryOptions {
+        SharedMemoryOptions::new()
+    }
+}
+
+/// A named POSIX shared memory region, created with `shm_open`.
+///
+/// Shared memory regions live in a namespace shared by all processes on the system (on Linux,
+/// they appear as files in `/dev/shm`), which allows unrelated processes to map the same memory.
+/// A region persists until its name is removed with [`unlink()`] and every memory map of it has
+/// been dropped.
+///
+/// Use [`SharedMemoryOptions`] to configure the permissions of created regions, or to remove
+/// the region's name when the handle is dropped.
+///
+/// Not supported on Android.
+///
+/// ## Safety
+///
+/// Memory maps of a shared memory region are created with `unsafe` methods, because other
+/// processes may modify the region while it is mapped. See [`MmapOptions`] for details.
+///
+/// ## Example
+///
+/// ```
+/// use memmap::SharedMemory;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let name = format!("/memmap-doc-{}", std::process::id());
+/// let shm = SharedMemory::create(&name, 13)?;
+/// let mut writer = unsafe { shm.map_mut()? };
+/// writer.copy_from_slice(b"Hello, world!");
+///
+/// // Typically in another process:
+/// let reader = unsafe { SharedMemory::open(&name)?.map()? };
+/// assert_eq!(b"Hello, world!", &reader[..]);
+///
+/// shm.unlink()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`unlink()`]: SharedMemory::unlink()
+pub struct SharedMemory {
+    name: String,
+    file: File,
+    len: usize,
+    unlink_on_drop: bool,
+}
+
+impl SharedMemory {
+    /// Creates a new shared memory region of `len` bytes, failing if the name is taken.
+    ///
+    /// This is equivalent to calling `SharedMemoryOptions::new().exclusive(true).create(name,
+    /// len)`.
+    pub fn create(name: &str, len: usize) -> Result<SharedMemory> {
+        SharedMemoryOptions::new().exclusive(true).create(name, len)
+    }
+
+    /// Creates a new shared memory region of `len` bytes, or opens an existing region.
+    ///
+    /// This is equivalent to calling `SharedMemoryOptions::new().create(name, len)`.
+    pub fn create_or_open(name: &str, len: usize) -> Result<SharedMemory> {
+        SharedMemoryOptions::new().create(name, len)
+    }
+
+    /// Opens an existing shared memory region.
+    ///
+    /// This is equivalent to calling `SharedMemoryOptions::new().open(name)`.
+    pub fn open(name: &str) -> Result<SharedMemory> {
+        SharedMemoryOptions::new().open(name)
+    }
+
+    /// Returns the name of the region.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the length of the region in bytes, as of when it was created or opened.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the region has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the shared memory object's file.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Creates a read-only memory map of the whole region.
+    ///
+    /// # Safety
+    ///
+    /// The region must not be modified while the memory map is alive. See the [`MmapOptions`]
+    /// type-level safety documentation for details.
+    pub unsafe fn map(&self) -> Result<Mmap> {
+        MmapOptions::new().len(self.len).map(&self.file)
+    }
+
+    /// Creates a writeable memory map of the whole region.
+    ///
+    /// # Safety
+    ///
+    /// The region must not be modified through other memory maps while the memory map is alive.
+    /// See the [`MmapOptions`] type-level safety documentation for details.
+    pub unsafe fn map_mut(&self) -> Result<MmapMut> {
+        MmapOptions::new().len(self.len).map_mut(&self.file)
+    }
+
+    /// Removes the name of the region.
+    ///
+    /// The region can no longer be opened, but existing memory maps and handles of it remain
+    /// valid. Its memory is released once they have all been dropped.
+    pub fn unlink(&self) -> Result<()> {
+        unix::shm_unlink(&self.name)
+    }
+
+    /// Removes the name of a shared memory region, without opening it.
+    pub fn unlink_name(name: &str) -> Result<()> {
+        unix::shm_unlink(name)
+    }
+}
+
+impl AsRawFd for SharedMemory {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        if self.unlink_on_drop {
+            let _ = unix::shm_unlink(&self.name);
+        }
+    }
+}
+
+impl fmt::Debug for SharedMemory {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SharedMemory")
+            .field("name", &self.name)
+            .field("fd", &self.file.as_raw_fd())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{SharedMemory, SharedMemoryOptions};
+
+    /// Returns a shared memory name which is unique to the test.
+    fn unique_name() -> String {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        format!(
+            "/memmap-test-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        )
+    }
+
+    #[test]
+    fn create_open_unlink() {
+        let name = unique_name();
+        let shm = SharedMemory::create(&name, 128).unwrap();
+        assert_eq!(name, shm.name());
+        assert_eq!(128, shm.len());
+
+        // Exclusive creation fails once the name is taken.
+        assert!(SharedMemory::create(&name, 128).is_err());
+
+        let mut writer = unsafe { shm.map_mut().unwrap() };
+        writer[..6].copy_from_slice(b"abc123");
+
+        let opened = SharedMemory::open(&name).unwrap();
+        assert_eq!(128, opened.len());
+        let reader = unsafe { opened.map().unwrap() };
+        assert_eq!(b"abc123", &reader[..6]);
+
+        shm.unlink().unwrap();
+        assert!(SharedMemory::open(&name).is_err());
+
+        // Memory maps remain valid after the name is removed.
+        writer[0] = b'x';
+        assert_eq!(b'x', reader[0]);
+    }
+
+    #[test]
+    fn create_or_open() {
+        let name = unique_name();
+        let shm = SharedMemory::create_or_open(&name, 128).unwrap();
+        unsafe { shm.map_mut().unwrap()[0] = 42 };
+
+        // Opening an existing region preserves its contents, and grows it if necessary.
+        let shm = SharedMemory::create_or_open(&name, 256).unwrap();
+        assert_eq!(256, shm.len());
+        assert_eq!(42, unsafe { shm.map().unwrap()[0] });
+
+        // Opening an existing region never shrinks it.
+        let shm = SharedMemory::create_or_open(&name, 64).unwrap();
+        assert_eq!(256, shm.len());
+
+        SharedMemory::unlink_name(&name).unwrap();
+    }
+
+    #[test]
+    fn unlink_on_drop() {
+        let name = unique_name();
+        let shm = SharedMemoryOptions::new()
+            .unlink_on_drop(true)
+            .create(&name, 128)
+            .unwrap();
+        let mmap = unsafe { shm.map_mut().unwrap() };
+        assert!(SharedMemory::open(&name).is_ok());
+
+        drop(shm);
+        assert!(SharedMemory::open(&name).is_err());
+        drop(mmap);
+    }
+
+    #[test]
+    fn mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let name = unique_name();
+        let shm = SharedMemoryOptions::new()
+            .mode(0o400)
+            .unlink_on_drop(true)
+            .create(&name, 128)
+            .unwrap();
+        let mode = shm.as_file().metadata().unwrap().permissions().mode();
+        assert_eq!(0o400, mode & 0o777);
+    }
+}
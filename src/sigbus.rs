@@ -0,0 +1,627 @@
+use std::any::Any;
+use std::arch::global_asm;
+use std::cell::UnsafeCell;
+use std::io::{Error, ErrorKind, Result};
+use std::mem::{self, MaybeUninit};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::Once;
+
+use libc;
+use {FromBytes, Mmap, MmapMut};
+
+// Bus errors are recovered from without `sigsetjmp`, which can not be called soundly from Rust.
+// Instead, the accesses which may fault run in the assembly routines below, and the signal
+// handler resumes the interrupted thread at a recovery point within them, by rewriting the
+// program counter (and stack pointer) of the signal's context. The routines return the address
+// of the bus error, which is never null, or 0.
+//
+// `memmap_sigbus_copy(dst, src, len)` copies `len` bytes from `src` to `dst`. Bus errors raised
+// between `memmap_sigbus_copy_start` and `memmap_sigbus_copy_end` resume at
+// `memmap_sigbus_copy_fixup`.
+//
+// `memmap_sigbus_catch(f, data, sp)` saves the callee-saved registers, stores its stack pointer
+// in `*sp`, and calls `f(data)`. Bus errors raised by the current thread while `f` runs resume
+// at `memmap_sigbus_catch_landing` with the stored stack pointer, which restores the registers
+// and returns, abandoning `f`.
+#[cfg(target_arch = "x86_64")]
+global_asm!(
+    ".pushsection .text.memmap_sigbus,\"ax\",@progbits",
+    ".globl memmap_sigbus_copy",
+    ".hidden memmap_sigbus_copy",
+    ".globl memmap_sigbus_copy_start",
+    ".hidden memmap_sigbus_copy_start",
+    ".globl memmap_sigbus_copy_end",
+    ".hidden memmap_sigbus_copy_end",
+    ".globl memmap_sigbus_copy_fixup",
+    ".hidden memmap_sigbus_copy_fixup",
+    ".globl memmap_sigbus_catch",
+    ".hidden memmap_sigbus_catch",
+    ".globl memmap_sigbus_catch_landing",
+    ".hidden memmap_sigbus_catch_landing",
+    ".p2align 4",
+    ".type memmap_sigbus_copy,@function",
+    "memmap_sigbus_copy:",
+    "    mov rcx, rdx",
+    "memmap_sigbus_copy_start:",
+    "    rep movsb",
+    "memmap_sigbus_copy_end:",
+    "    xor eax, eax",
+    "memmap_sigbus_copy_fixup:",
+    "    ret",
+    ".size memmap_sigbus_copy, . - memmap_sigbus_copy",
+    ".p2align 4",
+    ".type memmap_sigbus_catch,@function",
+    "memmap_sigbus_catch:",
+    "    push rbp",
+    "    push rbx",
+    "    push r12",
+    "    push r13",
+    "    push r14",
+    "    push r15",
+    // Realign the stack to 16 bytes for the call.
+    "    sub rsp, 8",
+    "    mov [rdx], rsp",
+    "    mov rax, rdi",
+    "    mov rdi, rsi",
+    "    call rax",
+    "    xor eax, eax",
+    "memmap_sigbus_catch_landing:",
+    "    add rsp, 8",
+    "    pop r15",
+    "    pop r14",
+    "    pop r13",
+    "    pop r12",
+    "    pop rbx",
+    "    pop rbp",
+    "    ret",
+    ".size memmap_sigbus_catch, . - memmap_sigbus_catch",
+    ".popsection",
+);
+
+#[cfg(target_arch = "aarch64")]
+global_asm!(
+    ".pushsection .text.memmap_sigbus,\"ax\",@progbits",
+    ".globl memmap_sigbus_copy",
+    ".hidden memmap_sigbus_copy",
+    ".globl memmap_sigbus_copy_start",
+    ".hidden memmap_sigbus_copy_start",
+    ".globl memmap_sigbus_copy_end",
+    ".hidden memmap_sigbus_copy_end",
+    ".globl memmap_sigbus_copy_fixup",
+    ".hidden memmap_sigbus_copy_fixup",
+    ".globl memmap_sigbus_catch",
+    ".hidden memmap_sigbus_catch",
+    ".globl memmap_sigbus_catch_landing",
+    ".hidden memmap_sigbus_catch_landing",
+    ".p2align 4",
+    ".type memmap_sigbus_copy,@function",
+    "memmap_sigbus_copy:",
+    "memmap_sigbus_copy_start:",
+    "    cmp x2, #8",
+    "    b.lo 2f",
+    "1:  ldr x3, [x1], #8",
+    "    str x3, [x0], #8",
+    "    sub x2, x2, #8",
+    "    cmp x2, #8",
+    "    b.hs 1b",
+    "2:  cbz x2, 4f",
+    "3:  ldrb w3, [x1], #1",
+    "    strb w3, [x0], #1",
+    "    subs x2, x2, #1",
+    "    b.ne 3b",
+    "4:",
+    "memmap_sigbus_copy_end:",
+    "    mov x0, #0",
+    "memmap_sigbus_copy_fixup:",
+    "    ret",
+    ".size memmap_sigbus_copy, . - memmap_sigbus_copy",
+    ".p2align 4",
+    ".type memmap_sigbus_catch,@function",
+    "memmap_sigbus_catch:",
+    "    stp x29, x30, [sp, #-160]!",
+    "    mov x29, sp",
+    "    stp x19, x20, [sp, #16]",
+    "    stp x21, x22, [sp, #32]",
+    "    stp x23, x24, [sp, #48]",
+    "    stp x25, x26, [sp, #64]",
+    "    stp x27, x28, [sp, #80]",
+    "    stp d8, d9, [sp, #96]",
+    "    stp d10, d11, [sp, #112]",
+    "    stp d12, d13, [sp, #128]",
+    "    stp d14, d15, [sp, #144]",
+    "    mov x9, sp",
+    "    str x9, [x2]",
+    "    mov x9, x0",
+    "    mov x0, x1",
+    "    blr x9",
+    "    mov x0, #0",
+    "memmap_sigbus_catch_landing:",
+    "    ldp d14, d15, [sp, #144]",
+    "    ldp d12, d13, [sp, #128]",
+    "    ldp d10, d11, [sp, #112]",
+    "    ldp d8, d9, [sp, #96]",
+    "    ldp x27, x28, [sp, #80]",
+    "    ldp x25, x26, [sp, #64]",
+    "    ldp x23, x24, [sp, #48]",
+    "    ldp x21, x22, [sp, #32]",
+    "    ldp x19, x20, [sp, #16]",
+    "    ldp x29, x30, [sp], #160",
+    "    ret",
+    ".size memmap_sigbus_catch, . - memmap_sigbus_catch",
+    ".popsection",
+);
+
+extern "C" {
+    fn memmap_sigbus_copy(dst: *mut u8, src: *const u8, len: usize) -> usize;
+    fn memmap_sigbus_catch(
+        f: extern "C" fn(*mut libc::c_void),
+        data: *mut libc::c_void,
+        sp: *mut usize,
+    ) -> usize;
+
+    // Labels within the routines, which are only compared against and resumed at.
+    #[link_name = "memmap_sigbus_copy_start"]
+    static COPY_START: u8;
+    #[link_name = "memmap_sigbus_copy_end"]
+    static COPY_END: u8;
+    #[link_name = "memmap_sigbus_copy_fixup"]
+    static COPY_FIXUP: u8;
+    #[link_name = "memmap_sigbus_catch_landing"]
+    static CATCH_LANDING: u8;
+}
+
+/// A recovery point of the current thread, in `memmap_sigbus_catch`.
+struct Recovery {
+    /// The stack pointer of `memmap_sigbus_catch`, to resume at its landing with.
+    sp: usize,
+    /// The enclosing recovery point of the current thread, or null.
+    outer: *mut Recovery,
+}
+
+// The recovery points are shared with the signal handler, so they are only accessed atomically.
+thread_local! {
+    /// The innermost recovery point of the current thread, or null.
+    static RECOVERY: AtomicPtr<Recovery> = const { AtomicPtr::new(ptr::null_mut()) };
+}
+
+/// The `SIGBUS` action which was installed before ours.
+struct Previous(UnsafeCell<MaybeUninit<libc::sigaction>>);
+
+// Written once, before `PREVIOUS_SET` is set, and only read afterwards.
+unsafe impl Sync for Previous {}
+
+static PREVIOUS: Previous = Previous(UnsafeCell::new(MaybeUninit::uninit()));
+static PREVIOUS_SET: AtomicBool = AtomicBool::new(false);
+
+static INSTALL: Once = Once::new();
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the `SIGBUS` handler, once per process.
+fn install() -> Result<()> {
+    let mut result = Ok(());
+    INSTALL.call_once(|| unsafe {
+        // Record the previous action before installing ours, so it is set whenever the handler
+        // runs.
+        let previous = (*PREVIOUS.0.get()).as_mut_ptr();
+        if libc::sigaction(libc::SIGBUS, ptr::null(), previous) != 0 {
+            result = Err(Error::last_os_error());
+            return;
+        }
+        PREVIOUS_SET.store(true, Ordering::Release);
+
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGBUS, &action, ptr::null_mut()) == 0 {
+            INSTALLED.store(true, Ordering::Release);
+        } else {
+            result = Err(Error::last_os_error());
+        }
+    });
+    result?;
+    if !INSTALLED.load(Ordering::Acquire) {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "failed to install the SIGBUS handler",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the program counter of the interrupted thread.
+#[cfg(target_arch = "x86_64")]
+unsafe fn pc(context: *mut libc::ucontext_t) -> usize {
+    (*context).uc_mcontext.gregs[libc::REG_RIP as usize] as usize
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn pc(context: *mut libc::ucontext_t) -> usize {
+    (*context).uc_mcontext.pc as usize
+}
+
+/// Resumes the interrupted thread at `pc`, with the stack pointer `sp` if given, returning
+/// `value`.
+#[cfg(target_arch = "x86_64")]
+unsafe fn resume(context: *mut libc::ucontext_t, pc: usize, sp: Option<usize>, value: usize) {
+    let gregs = &mut (*context).uc_mcontext.gregs;
+    gregs[libc::REG_RIP as usize] = pc as libc::greg_t;
+    if let Some(sp) = sp {
+        gregs[libc::REG_RSP as usize] = sp as libc::greg_t;
+    }
+    gregs[libc::REG_RAX as usize] = value as libc::greg_t;
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn resume(context: *mut libc::ucontext_t, pc: usize, sp: Option<usize>, value: usize) {
+    let mcontext = &mut (*context).uc_mcontext;
+    mcontext.pc = pc as _;
+    if let Some(sp) = sp {
+        mcontext.sp = sp as _;
+    }
+    mcontext.regs[0] = value as _;
+}
+
+/// Handles `SIGBUS`.
+///
+/// If the signal was raised by an access to a non-existent page of a file (`BUS_ADRERR` or
+/// `BUS_OBJERR`), either by `memmap_sigbus_copy` or while the current thread has a recovery
+/// point, the thread is resumed at the corresponding recovery point, returning the address of the
+/// bus error. Otherwise, the signal is passed on to the previous action.
+extern "C" fn handler(signal: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    unsafe {
+        let code = (*info).si_code;
+        if code == libc::BUS_ADRERR || code == libc::BUS_OBJERR {
+            let context = context as *mut libc::ucontext_t;
+            let addr = (*info).si_addr() as usize;
+            let pc = pc(context);
+            if ptr::addr_of!(COPY_START) as usize <= pc && pc < ptr::addr_of!(COPY_END) as usize {
+                resume(context, ptr::addr_of!(COPY_FIXUP) as usize, None, addr);
+                return;
+            }
+            let recovery = RECOVERY
+                .try_with(|recovery| recovery.load(Ordering::SeqCst))
+                .unwrap_or(ptr::null_mut());
+            if !recovery.is_null() {
+                let sp = ptr::read_volatile(&(*recovery).sp);
+                resume(
+                    context,
+                    ptr::addr_of!(CATCH_LANDING) as usize,
+                    Some(sp),
+                    addr,
+                );
+                return;
+            }
+        }
+
+        let previous = if PREVIOUS_SET.load(Ordering::Acquire) {
+            Some(&*(*PREVIOUS.0.get()).as_ptr())
+        } else {
+            None
+        };
+        match previous {
+            Some(previous)
+                if previous.sa_sigaction != libc::SIG_DFL
+                    && previous.sa_sigaction != libc::SIG_IGN =>
+            {
+                if previous.sa_flags & libc::SA_SIGINFO != 0 {
+                    let previous: extern "C" fn(
+                        libc::c_int,
+                        *mut libc::siginfo_t,
+                        *mut libc::c_void,
+                    ) = mem::transmute(previous.sa_sigaction);
+                    previous(signal, info, context);
+                } else {
+                    let previous: extern "C" fn(libc::c_int) =
+                        mem::transmute(previous.sa_sigaction);
+                    previous(signal);
+                }
+            }
+            previous => {
+                // Restore the previous action (by default, terminating the process), which
+                // takes effect when the faulting access is retried.
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = libc::SIG_DFL;
+                libc::sigaction(
+                    signal,
+                    previous.map_or(&action, |previous| previous),
+                    ptr::null_mut(),
+                );
+            }
+        }
+    }
+}
+
+/// A closure called by `memmap_sigbus_catch`, and the payload of its panic, if any.
+struct Call<'a> {
+    f: &'a mut dyn FnMut(),
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// Calls the closure of a `Call`, catching panics, which must not unwind through
+/// `memmap_sigbus_catch`.
+extern "C" fn call(data: *mut libc::c_void) {
+    let call = unsafe { &mut *(data as *mut Call) };
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| (call.f)())) {
+        call.panic = Some(payload);
+    }
+}
+
+/// Runs `f` with a recovery point installed for the current thread.
+///
+/// Returns the address of the bus error which `f` was abandoned at, or 0 if it completed. Panics
+/// raised by `f` are propagated.
+unsafe fn recover(f: &mut dyn FnMut()) -> usize {
+    let mut recovery = Recovery {
+        sp: 0,
+        outer: RECOVERY.with(|current| current.load(Ordering::SeqCst)),
+    };
+    let recovery: *mut Recovery = &mut recovery;
+    RECOVERY.with(|current| current.store(recovery, Ordering::SeqCst));
+    let mut call = Call { f, panic: None };
+    let fault = memmap_sigbus_catch(
+        self::call,
+        &mut call as *mut Call as *mut libc::c_void,
+        ptr::addr_of_mut!((*recovery).sp),
+    );
+    RECOVERY.with(|current| current.store((*recovery).outer, Ordering::SeqCst));
+    if let Some(payload) = call.panic {
+        panic::resume_unwind(payload);
+    }
+    fault
+}
+
+/// Returns the error for a bus error at `fault`.
+fn bus_error(fault: usize) -> Error {
+    Error::new(
+        ErrorKind::UnexpectedEof,
+        format!(
+            "bus error accessing memory mapped address {:#x}; the mapped file may have been \
+             truncated",
+            fault
+        ),
+    )
+}
+
+/// Runs `f`, turning bus errors (`SIGBUS`) raised by it on the current thread into an error.
+///
+/// Accessing a page of a file-backed memory map which lies past the end of the file raises
+/// `SIGBUS`, which terminates the process by default. This happens, for instance, when another
+/// process truncates a mapped file. While `f` runs, such bus errors are caught by a process-wide
+/// `SIGBUS` handler, which is installed on first use and passes other signals on to the
+/// previously installed action.
+///
+/// When `f` raises a bus error, it is abandoned: the thread jumps out of it back into
+/// `catch_bus_errors`, which returns an error. The memory map is left untouched, so accessing
+/// the missing pages again raises another bus error.
+///
+/// Prefer the `try_copy_to` and `try_read_at` methods of [`Mmap`] and [`MmapMut`], which are
+/// safe, since they access the memory map with a dedicated copy routine rather than abandoning
+/// arbitrary code.
+///
+/// Only supported on Linux, on x86-64 and AArch64.
+///
+/// # Safety
+///
+/// Abandoning `f` skips the rest of it without unwinding. No destructors run, and locks held by
+/// `f` are never released. `f` must not own values with destructors, or hold locks or
+/// references to state it is modifying, at any point where it may raise a bus error. Since the
+/// compiler assumes that reading through a reference can not fault, it may move such reads
+/// around within `f`; access the memory map through raw pointers and volatile reads instead.
+/// Panics raised by `f` are propagated.
+///
+/// # Errors
+///
+/// This method returns an error of kind `UnexpectedEof` if `f` raised a bus error, or an error
+/// if the `SIGBUS` handler can not be installed.
+///
+/// # Example
+///
+/// ```
+/// # extern crate memmap;
+/// # extern crate tempdir;
+/// #
+/// use std::fs::OpenOptions;
+/// use std::ptr;
+///
+/// use memmap::{catch_bus_errors, MmapOptions};
+/// #
+/// # fn main() -> std::io::Result<()> {
+/// # let tempdir = tempdir::TempDir::new("mmap")?;
+/// # let path = tempdir.path().join("catch_bus_errors");
+/// let file = OpenOptions::new().read(true).write(true).create(true).open(&path)?;
+/// file.set_len(4096)?;
+/// let mmap = unsafe { MmapOptions::new().map(&file)? };
+///
+/// // Another process truncates the file.
+/// file.set_len(0)?;
+///
+/// let ptr = mmap.as_ptr();
+/// assert!(unsafe { catch_bus_errors(|| ptr::read_volatile(ptr)) }.is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub unsafe fn catch_bus_errors<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> R,
+{
+    install()?;
+    let mut f = Some(f);
+    let mut result = None;
+    match recover(&mut || result = f.take().map(|f| f())) {
+        // `f` completed, so its result is set.
+        0 => Ok(result.unwrap()),
+        fault => Err(bus_error(fault)),
+    }
+}
+
+/// Copies `len` bytes at `offset` in the memory map at `ptr` to `dst`, catching bus errors
+/// raised by the copy.
+fn try_copy(ptr: *const u8, map_len: usize, offset: usize, dst: *mut u8, len: usize) -> Result<()> {
+    if offset.checked_add(len).map_or(true, |end| end > map_len) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "range is out of the bounds of the memory map",
+        ));
+    }
+    install()?;
+    match unsafe { memmap_sigbus_copy(dst, ptr.add(offset), len) } {
+        0 => Ok(()),
+        fault => Err(bus_error(fault)),
+    }
+}
+
+/// Reads the `T` at `offset` in the memory map at `ptr`, catching bus errors.
+fn try_read_at<T: FromBytes>(ptr: *const u8, map_len: usize, offset: usize) -> Result<T> {
+    let mut value = MaybeUninit::<T>::uninit();
+    try_copy(
+        ptr,
+        map_len,
+        offset,
+        value.as_mut_ptr() as *mut u8,
+        mem::size_of::<T>(),
+    )?;
+    // Any bit pattern is a valid `T`.
+    Ok(unsafe { value.assume_init() })
+}
+
+macro_rules! impl_try_accessors {
+    ($map:ident) => {
+        impl $map {
+            /// Copies bytes starting at `offset` in the memory map into `buf`, turning bus errors
+            /// into an error.
+            ///
+            /// Only bus errors raised by the copy are caught; the memory map is left untouched.
+            /// Only supported on Linux, on x86-64 and AArch64. See
+            /// [`catch_bus_errors()`](crate::catch_bus_errors()).
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the range is out of the bounds of the memory
+            /// map, or when accessing it raised a bus error, for instance because the mapped file
+            /// was truncated.
+            pub fn try_copy_to(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+                try_copy(
+                    self.inner.ptr(),
+                    self.inner.len(),
+                    offset,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                )
+            }
+
+            /// Reads the value of type `T` at `offset` in the memory map, turning bus errors into
+            /// an error.
+            ///
+            /// The offset does not need to be aligned. Only supported on Linux, on x86-64 and
+            /// AArch64. See
+            /// [`try_copy_to()`](Self::try_copy_to()).
+            ///
+            /// # Errors
+            ///
+            /// This method returns an error when the value is out of the bounds of the memory
+            /// map, or when accessing it raised a bus error, for instance because the mapped file
+            /// was truncated.
+            pub fn try_read_at<T: FromBytes>(&self, offset: usize) -> Result<T> {
+                try_read_at(self.inner.ptr(), self.inner.len(), offset)
+            }
+        }
+    };
+}
+
+impl_try_accessors!(Mmap);
+impl_try_accessors!(MmapMut);
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    use std::fs::{File, OpenOptions};
+    use std::io::ErrorKind;
+    use std::{panic, ptr};
+
+    use super::catch_bus_errors;
+    use MmapOptions;
+
+    fn file(tempdir: &tempdir::TempDir, len: u64) -> File {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(tempdir.path().join("mmap"))
+            .unwrap();
+        file.set_len(len).unwrap();
+        file
+    }
+
+    #[test]
+    fn try_accessors() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = file(&tempdir, 12288);
+        let mut mmap = unsafe { MmapOptions::new().map_mut(&file).unwrap() };
+        mmap[4096..4104].copy_from_slice(&42u64.to_ne_bytes());
+
+        let mut buf = [1; 16];
+        mmap.try_copy_to(0, &mut buf).unwrap();
+        assert_eq!([0; 16], buf);
+        assert_eq!(42, mmap.try_read_at::<u64>(4096).unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            mmap.try_read_at::<u64>(12286).unwrap_err().kind()
+        );
+
+        // Truncate all but the first page away.
+        file.set_len(4096).unwrap();
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            mmap.try_read_at::<u64>(4096).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            mmap.try_copy_to(8180, &mut buf).unwrap_err().kind()
+        );
+        // The first page is still backed by the file.
+        mmap.try_copy_to(0, &mut buf).unwrap();
+
+        // The truncated pages are left alone, so they are backed by the file again once it
+        // grows back.
+        file.set_len(12288).unwrap();
+        assert_eq!(0, mmap.try_read_at::<u64>(4096).unwrap());
+    }
+
+    #[test]
+    fn catch_nested() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = file(&tempdir, 4096);
+        let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+        file.set_len(0).unwrap();
+
+        let ptr = mmap.as_ptr();
+        let result = unsafe {
+            catch_bus_errors(|| {
+                assert_eq!(7, catch_bus_errors(|| 7).unwrap());
+                assert!(mmap.try_read_at::<u8>(0).is_err());
+                ptr::read_volatile(ptr)
+            })
+        };
+        assert_eq!(ErrorKind::UnexpectedEof, result.unwrap_err().kind());
+        assert!(mmap.try_read_at::<u8>(0).is_err());
+    }
+
+    #[test]
+    fn catch_panic() {
+        let result = panic::catch_unwind(|| unsafe { catch_bus_errors(|| panic!("panic")) });
+        assert!(result.is_err());
+
+        // The recovery point of the panicking closure was removed.
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let file = file(&tempdir, 4096);
+        let mmap = unsafe { MmapOptions::new().map(&file).unwrap() };
+        file.set_len(0).unwrap();
+        let ptr = mmap.as_ptr();
+        assert!(unsafe { catch_bus_errors(|| ptr::read_volatile(ptr)) }.is_err());
+    }
+}
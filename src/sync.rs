@@ -0,0 +1,712 @@
+//! Process-shared synchronization primitives which live inside a memory map.
+
+use std::cell::UnsafeCell;
+use std::io::{Error, Result};
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::sync::{LockResult, PoisonError, TryLockError, TryLockResult};
+use std::time::Duration;
+
+use libc;
+use {view, MmapMut};
+
+/// Converts the return value of a pthread function to a result.
+fn cvt(code: libc::c_int) -> Result<()> {
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(Error::from_raw_os_error(code))
+    }
+}
+
+/// Returns a reference to the `T` at `offset` in the memory map, checking that it is in bounds
+/// and aligned.
+unsafe fn place<T>(mmap: &MmapMut, offset: usize) -> Result<&T> {
+    view::check::<T>(mmap.as_ptr(), mmap.len(), offset, mem::size_of::<T>())?;
+    Ok(&*(mmap.as_ptr().add(offset) as *const T))
+}
+
+/// Returns the absolute time `timeout` from now on `clock`.
+fn deadline(clock: libc::clockid_t, timeout: Duration) -> libc::timespec {
+    let mut now = MaybeUninit::<libc::timespec>::uninit();
+    unsafe {
+        libc::clock_gettime(clock, now.as_mut_ptr());
+        let now = now.assume_init();
+        let nanos = now.tv_nsec as u64 + u64::from(timeout.subsec_nanos());
+        let secs = (now.tv_sec as u64)
+            .saturating_add(timeout.as_secs())
+            .saturating_add(nanos / 1_000_000_000)
+            .min(libc::time_t::MAX as u64);
+        libc::timespec {
+            tv_sec: secs as libc::time_t,
+            tv_nsec: (nanos % 1_000_000_000) as _,
+        }
+    }
+}
+
+/// A robust, process-shared mutual exclusion lock which lives inside a memory map.
+///
+/// The mutex is a `pthread_mutex_t` configured with `PTHREAD_PROCESS_SHARED` and
+/// `PTHREAD_MUTEX_ROBUST`, placed at an offset of a memory map which is shared between
+/// processes, such as a shared anonymous memory map inherited across `fork`, a memory file, or a
+/// [`SharedMemory`] region. Unlike `std::sync::Mutex`, it does not wrap the data it protects; the
+/// protected data is typically elsewhere in the same memory map.
+///
+/// If a process or thread dies while holding the lock, the next locker acquires the lock with a
+/// [`PoisonError`], signaling that the protected data may be inconsistent. The lock is made
+/// consistent again by the poisoned acquisition, so later lockers are not affected.
+///
+/// The mutex occupies `mem::size_of::<Mutex>()` bytes, aligned to `mem::align_of::<Mutex>()`.
+///
+/// Only supported on Linux.
+///
+/// ## Example
+///
+/// ```
+/// use memmap::MmapOptions;
+/// use memmap::sync::Mutex;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mmap = MmapOptions::new().len(4096).map_anon()?;
+/// let mutex = unsafe { Mutex::init(&mmap, 0)? };
+/// {
+///     let _guard = mutex.lock().unwrap();
+///     // Access the protected data.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SharedMemory`]: crate::SharedMemory
+#[repr(transparent)]
+pub struct Mutex {
+    raw: UnsafeCell<libc::pthread_mutex_t>,
+}
+
+unsafe impl Send for Mutex {}
+unsafe impl Sync for Mutex {}
+
+impl Mutex {
+    /// Initializes a mutex at `offset` in the memory map, and returns a reference to it.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the mutex is out of the bounds of the memory map, when
+    /// `offset` is not aligned for the mutex, or when the underlying pthread call fails.
+    ///
+    /// # Safety
+    ///
+    /// No process may be using a synchronization primitive at `offset` of the memory map while
+    /// the mutex is initialized, and the bytes of the mutex must not be accessed other than
+    /// through the mutex while it is in use.
+    pub unsafe fn init(mmap: &MmapMut, offset: usize) -> Result<&Mutex> {
+        let mutex = place::<Mutex>(mmap, offset)?;
+        let mut attr = MaybeUninit::<libc::pthread_mutexattr_t>::uninit();
+        cvt(libc::pthread_mutexattr_init(attr.as_mut_ptr()))?;
+        let result = cvt(libc::pthread_mutexattr_setpshared(
+            attr.as_mut_ptr(),
+            libc::PTHREAD_PROCESS_SHARED,
+        ))
+        .and_then(|_| {
+            cvt(libc::pthread_mutexattr_setrobust(
+                attr.as_mut_ptr(),
+                libc::PTHREAD_MUTEX_ROBUST,
+            ))
+        })
+        .and_then(|_| cvt(libc::pthread_mutex_init(mutex.raw.get(), attr.as_ptr())));
+        libc::pthread_mutexattr_destroy(attr.as_mut_ptr());
+        result.map(|_| mutex)
+    }
+
+    /// Returns a reference to the mutex at `offset` in the memory map.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the mutex is out of the bounds of the memory map, or
+    /// when `offset` is not aligned for the mutex.
+    ///
+    /// # Safety
+    ///
+    /// The mutex must have been initialized with [`Mutex::init()`], by this or another process,
+    /// and the bytes of the mutex must not be accessed other than through the mutex.
+    pub unsafe fn from_mmap(mmap: &MmapMut, offset: usize) -> Result<&Mutex> {
+        place::<Mutex>(mmap, offset)
+    }
+
+    /// Acquires the mutex, blocking the current thread until it is able to do so.
+    ///
+    /// # Errors
+    ///
+    /// If the previous owner of the mutex died while holding it, the mutex is acquired, made
+    /// consistent, and returned in a [`PoisonError`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the underlying pthread call fails for any other reason.
+    pub fn lock(&self) -> LockResult<MutexGuard<'_>> {
+        let code = unsafe { libc::pthread_mutex_lock(self.raw.get()) };
+        self.guard(code)
+            .unwrap_or_else(|error| panic!("failed to lock mutex: {}", error))
+    }
+
+    /// Attempts to acquire the mutex without blocking.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`TryLockError::WouldBlock`] when the mutex is held by another
+    /// thread. If the previous owner of the mutex died while holding it, the mutex is acquired,
+    /// made consistent, and returned in a [`TryLockError::Poisoned`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the underlying pthread call fails for any other reason.
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_>> {
+        let code = unsafe { libc::pthread_mutex_trylock(self.raw.get()) };
+        if code == libc::EBUSY {
+            return Err(TryLockError::WouldBlock);
+        }
+        match self.guard(code) {
+            Ok(Ok(guard)) => Ok(guard),
+            Ok(Err(poisoned)) => Err(TryLockError::Poisoned(poisoned)),
+            Err(error) => panic!("failed to lock mutex: {}", error),
+        }
+    }
+
+    /// Returns a guard for a lock acquisition which returned `code`.
+    fn guard(&self, code: libc::c_int) -> Result<LockResult<MutexGuard<'_>>> {
+        let guard = MutexGuard {
+            mutex: self,
+            _marker: PhantomData,
+        };
+        match code {
+            0 => Ok(Ok(guard)),
+            libc::EOWNERDEAD => {
+                cvt(unsafe { libc::pthread_mutex_consistent(self.raw.get()) })?;
+                Ok(Err(PoisonError::new(guard)))
+            }
+            code => {
+                mem::forget(guard);
+                Err(Error::from_raw_os_error(code))
+            }
+        }
+    }
+}
+
+/// A guard which releases a [`Mutex`] when dropped.
+#[must_use = "if unused the Mutex will immediately unlock"]
+pub struct MutexGuard<'a> {
+    mutex: &'a Mutex,
+    /// The mutex must be released by the thread which acquired it.
+    _marker: PhantomData<*mut ()>,
+}
+
+impl Drop for MutexGuard<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::pthread_mutex_unlock(self.mutex.raw.get());
+        }
+    }
+}
+
+/// A process-shared reader-writer lock which lives inside a memory map.
+///
+/// The lock is a `pthread_rwlock_t` configured with `PTHREAD_PROCESS_SHARED`. See [`Mutex`] for
+/// how it is placed in a memory map. Unlike [`Mutex`], the lock is not robust: if a process dies
+/// while holding the lock, other processes may deadlock.
+///
+/// Only supported on Linux.
+#[repr(transparent)]
+pub struct RwLock {
+    raw: UnsafeCell<libc::pthread_rwlock_t>,
+}
+
+unsafe impl Send for RwLock {}
+unsafe impl Sync for RwLock {}
+
+impl RwLock {
+    /// Initializes a reader-writer lock at `offset` in the memory map, and returns a reference
+    /// to it.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the lock is out of the bounds of the memory map, when
+    /// `offset` is not aligned for the lock, or when the underlying pthread call fails.
+    ///
+    /// # Safety
+    ///
+    /// See [`Mutex::init()`].
+    pub unsafe fn init(mmap: &MmapMut, offset: usize) -> Result<&RwLock> {
+        let lock = place::<RwLock>(mmap, offset)?;
+        let mut attr = MaybeUninit::<libc::pthread_rwlockattr_t>::uninit();
+        cvt(libc::pthread_rwlockattr_init(attr.as_mut_ptr()))?;
+        let result = cvt(libc::pthread_rwlockattr_setpshared(
+            attr.as_mut_ptr(),
+            libc::PTHREAD_PROCESS_SHARED,
+        ))
+        .and_then(|_| cvt(libc::pthread_rwlock_init(lock.raw.get(), attr.as_ptr())));
+        libc::pthread_rwlockattr_destroy(attr.as_mut_ptr());
+        result.map(|_| lock)
+    }
+
+    /// Returns a reference to the reader-writer lock at `offset` in the memory map.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the lock is out of the bounds of the memory map, or
+    /// when `offset` is not aligned for the lock.
+    ///
+    /// # Safety
+    ///
+    /// The lock must have been initialized with [`RwLock::init()`], by this or another process,
+    /// and the bytes of the lock must not be accessed other than through the lock.
+    pub unsafe fn from_mmap(mmap: &MmapMut, offset: usize) -> Result<&RwLock> {
+        place::<RwLock>(mmap, offset)
+    }
+
+    /// Acquires the lock with shared read access, blocking the current thread until it is able
+    /// to do so.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the underlying pthread call fails, for instance when the current
+    /// thread already holds the lock for writing.
+    pub fn read(&self) -> RwLockReadGuard<'_> {
+        let code = unsafe { libc::pthread_rwlock_rdlock(self.raw.get()) };
+        cvt(code).unwrap_or_else(|error| panic!("failed to lock rwlock: {}", error));
+        RwLockReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attempts to acquire the lock with shared read access without blocking.
+    ///
+    /// Returns `None` when the lock is held for writing.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_>> {
+        match unsafe { libc::pthread_rwlock_tryrdlock(self.raw.get()) } {
+            0 => Some(RwLockReadGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Acquires the lock with exclusive write access, blocking the current thread until it is
+    /// able to do so.
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the underlying pthread call fails, for instance when the current
+    /// thread already holds the lock.
+    pub fn write(&self) -> RwLockWriteGuard<'_> {
+        let code = unsafe { libc::pthread_rwlock_wrlock(self.raw.get()) };
+        cvt(code).unwrap_or_else(|error| panic!("failed to lock rwlock: {}", error));
+        RwLockWriteGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Attempts to acquire the lock with exclusive write access without blocking.
+    ///
+    /// Returns `None` when the lock is held for reading or writing.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_>> {
+        match unsafe { libc::pthread_rwlock_trywrlock(self.raw.get()) } {
+            0 => Some(RwLockWriteGuard {
+                lock: self,
+                _marker: PhantomData,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A guard which releases the shared read access of a [`RwLock`] when dropped.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a> {
+    lock: &'a RwLock,
+    _marker: PhantomData<*mut ()>,
+}
+
+impl Drop for RwLockReadGuard<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::pthread_rwlock_unlock(self.lock.raw.get());
+        }
+    }
+}
+
+/// A guard which releases the exclusive write access of a [`RwLock`] when dropped.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a> {
+    lock: &'a RwLock,
+    _marker: PhantomData<*mut ()>,
+}
+
+impl Drop for RwLockWriteGuard<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::pthread_rwlock_unlock(self.lock.raw.get());
+        }
+    }
+}
+
+/// A process-shared condition variable which lives inside a memory map.
+///
+/// The condition variable is a `pthread_cond_t` configured with `PTHREAD_PROCESS_SHARED`, and
+/// timing out against `CLOCK_MONOTONIC`. It is used together with a [`Mutex`]. See [`Mutex`] for
+/// how it is placed in a memory map.
+///
+/// Only supported on Linux.
+#[repr(transparent)]
+pub struct Condvar {
+    raw: UnsafeCell<libc::pthread_cond_t>,
+}
+
+unsafe impl Send for Condvar {}
+unsafe impl Sync for Condvar {}
+
+impl Condvar {
+    /// Initializes a condition variable at `offset` in the memory map, and returns a reference
+    /// to it.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the condition variable is out of the bounds of the
+    /// memory map, when `offset` is not aligned for the condition variable, or when the
+    /// underlying pthread call fails.
+    ///
+    /// # Safety
+    ///
+    /// See [`Mutex::init()`].
+    pub unsafe fn init(mmap: &MmapMut, offset: usize) -> Result<&Condvar> {
+        let condvar = place::<Condvar>(mmap, offset)?;
+        let mut attr = MaybeUninit::<libc::pthread_condattr_t>::uninit();
+        cvt(libc::pthread_condattr_init(attr.as_mut_ptr()))?;
+        let result = cvt(libc::pthread_condattr_setpshared(
+            attr.as_mut_ptr(),
+            libc::PTHREAD_PROCESS_SHARED,
+        ))
+        .and_then(|_| {
+            cvt(libc::pthread_condattr_setclock(
+                attr.as_mut_ptr(),
+                libc::CLOCK_MONOTONIC,
+            ))
+        })
+        .and_then(|_| cvt(libc::pthread_cond_init(condvar.raw.get(), attr.as_ptr())));
+        libc::pthread_condattr_destroy(attr.as_mut_ptr());
+        result.map(|_| condvar)
+    }
+
+    /// Returns a reference to the condition variable at `offset` in the memory map.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the condition variable is out of the bounds of the
+    /// memory map, or when `offset` is not aligned for the condition variable.
+    ///
+    /// # Safety
+    ///
+    /// The condition variable must have been initialized with [`Condvar::init()`], by this or
+    /// another process, and the bytes of the condition variable must not be accessed other than
+    /// through the condition variable.
+    pub unsafe fn from_mmap(mmap: &MmapMut, offset: usize) -> Result<&Condvar> {
+        place::<Condvar>(mmap, offset)
+    }
+
+    /// Blocks the current thread until the condition variable is notified.
+    ///
+    /// The mutex of `guard` is released while waiting, and reacquired before returning. Spurious
+    /// wakeups are possible, so the condition should be checked in a loop.
+    ///
+    /// # Errors
+    ///
+    /// If the owner of the mutex died while holding it, the mutex is reacquired, made
+    /// consistent, and returned in a [`PoisonError`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics when the underlying pthread call fails for any other reason.
+    pub fn wait<'a>(&self, guard: MutexGuard<'a>) -> LockResult<MutexGuard<'a>> {
+        let mutex = guard.mutex;
+        mem::forget(guard);
+        let code = unsafe { libc::pthread_cond_wait(self.raw.get(), mutex.raw.get()) };
+        mutex
+            .guard(code)
+            .unwrap_or_else(|error| panic!("failed to wait on condvar: {}", error))
+    }
+
+    /// Blocks the current thread until the condition variable is notified, or until `timeout`
+    /// has elapsed.
+    ///
+    /// Returns the guard, and whether the wait timed out. See [`Condvar::wait()`].
+    pub fn wait_timeout<'a>(
+        &self,
+        guard: MutexGuard<'a>,
+        timeout: Duration,
+    ) -> LockResult<(MutexGuard<'a>, bool)> {
+        let mutex = guard.mutex;
+        mem::forget(guard);
+        let deadline = deadline(libc::CLOCK_MONOTONIC, timeout);
+        let code =
+            unsafe { libc::pthread_cond_timedwait(self.raw.get(), mutex.raw.get(), &deadline) };
+        let timed_out = code == libc::ETIMEDOUT;
+        let result = mutex
+            .guard(if timed_out { 0 } else { code })
+            .unwrap_or_else(|error| panic!("failed to wait on condvar: {}", error));
+        match result {
+            Ok(guard) => Ok((guard, timed_out)),
+            Err(poisoned) => Err(PoisonError::new((poisoned.into_inner(), timed_out))),
+        }
+    }
+
+    /// Wakes up one thread blocked on the condition variable.
+    pub fn notify_one(&self) {
+        unsafe {
+            libc::pthread_cond_signal(self.raw.get());
+        }
+    }
+
+    /// Wakes up all threads blocked on the condition variable.
+    pub fn notify_all(&self) {
+        unsafe {
+            libc::pthread_cond_broadcast(self.raw.get());
+        }
+    }
+}
+
+/// A process-shared counting semaphore which lives inside a memory map.
+///
+/// The semaphore is an unnamed POSIX semaphore (see `sem_init(3)`) with `pshared` set. See
+/// [`Mutex`] for how it is placed in a memory map.
+///
+/// Only supported on Linux.
+#[repr(transparent)]
+pub struct Semaphore {
+    raw: UnsafeCell<libc::sem_t>,
+}
+
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    /// Initializes a semaphore with the given value at `offset` in the memory map, and returns
+    /// a reference to it.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the semaphore is out of the bounds of the memory map,
+    /// when `offset` is not aligned for the semaphore, or when the underlying system call fails.
+    ///
+    /// # Safety
+    ///
+    /// See [`Mutex::init()`].
+    pub unsafe fn init(mmap: &MmapMut, offset: usize, value: u32) -> Result<&Semaphore> {
+        let semaphore = place::<Semaphore>(mmap, offset)?;
+        if libc::sem_init(semaphore.raw.get(), 1, value) != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(semaphore)
+    }
+
+    /// Returns a reference to the semaphore at `offset` in the memory map.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the semaphore is out of the bounds of the memory map,
+    /// or when `offset` is not aligned for the semaphore.
+    ///
+    /// # Safety
+    ///
+    /// The semaphore must have been initialized with [`Semaphore::init()`], by this or another
+    /// process, and the bytes of the semaphore must not be accessed other than through the
+    /// semaphore.
+    pub unsafe fn from_mmap(mmap: &MmapMut, offset: usize) -> Result<&Semaphore> {
+        place::<Semaphore>(mmap, offset)
+    }
+
+    /// Decrements the semaphore, blocking the current thread until its value is positive.
+    pub fn wait(&self) {
+        while unsafe { libc::sem_wait(self.raw.get()) } != 0 {
+            let error = Error::last_os_error();
+            assert_eq!(
+                Some(libc::EINTR),
+                error.raw_os_error(),
+                "failed to wait on semaphore: {}",
+                error
+            );
+        }
+    }
+
+    /// Attempts to decrement the semaphore without blocking.
+    ///
+    /// Returns `false` if the value of the semaphore is zero.
+    pub fn try_wait(&self) -> bool {
+        unsafe { libc::sem_trywait(self.raw.get()) == 0 }
+    }
+
+    /// Decrements the semaphore, blocking the current thread until its value is positive or
+    /// until `timeout` has elapsed.
+    ///
+    /// Returns `false` if the wait timed out. The timeout is measured against the system clock.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = deadline(libc::CLOCK_REALTIME, timeout);
+        loop {
+            if unsafe { libc::sem_timedwait(self.raw.get(), &deadline) } == 0 {
+                return true;
+            }
+            let error = Error::last_os_error();
+            match error.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::ETIMEDOUT) => return false,
+                _ => panic!("failed to wait on semaphore: {}", error),
+            }
+        }
+    }
+
+    /// Increments the semaphore, waking up a thread blocked on it.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error when the value of the semaphore would overflow.
+    pub fn post(&self) -> Result<()> {
+        if unsafe { libc::sem_post(self.raw.get()) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    /// Returns the current value of the semaphore.
+    pub fn value(&self) -> u32 {
+        let mut value = 0;
+        unsafe {
+            libc::sem_getvalue(self.raw.get(), &mut value);
+        }
+        value.max(0) as u32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::TryLockError;
+    use std::thread;
+    use std::time::Duration;
+
+    use libc;
+
+    use super::{Condvar, Mutex, RwLock, Semaphore};
+    use MmapOptions;
+
+    #[test]
+    fn mutex() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let mutex = unsafe { Mutex::init(&mmap, 64).unwrap() };
+        let other = unsafe { Mutex::from_mmap(&mmap, 64).unwrap() };
+
+        let guard = mutex.lock().unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| match other.try_lock() {
+                Err(TryLockError::WouldBlock) => {}
+                _ => panic!("mutex was not locked"),
+            });
+        });
+        drop(guard);
+        drop(other.try_lock().unwrap());
+
+        assert!(unsafe { Mutex::init(&mmap, 1) }.is_err());
+        assert!(unsafe { Mutex::from_mmap(&mmap, 4096) }.is_err());
+    }
+
+    #[test]
+    fn mutex_owner_died() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let mutex = unsafe { Mutex::init(&mmap, 0).unwrap() };
+
+        unsafe {
+            let pid = libc::fork();
+            assert!(pid >= 0);
+            if pid == 0 {
+                // The child dies while holding the lock.
+                mem::forget(mutex.lock());
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            assert_eq!(pid, libc::waitpid(pid, &mut status, 0));
+        }
+
+        // The lock is poisoned once, and then consistent again.
+        assert!(mutex.lock().is_err());
+        assert!(mutex.lock().is_ok());
+    }
+
+    #[test]
+    fn rwlock() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let lock = unsafe { RwLock::init(&mmap, 0).unwrap() };
+
+        let first = lock.read();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                assert!(lock.try_read().is_some());
+                assert!(lock.try_write().is_none());
+            });
+        });
+        drop(first);
+
+        let write = lock.write();
+        thread::scope(|scope| {
+            scope.spawn(|| assert!(lock.try_read().is_none()));
+        });
+        drop(write);
+        assert!(lock.try_write().is_some());
+    }
+
+    #[test]
+    fn condvar() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let mutex = unsafe { Mutex::init(&mmap, 0).unwrap() };
+        let condvar = unsafe { Condvar::init(&mmap, 128).unwrap() };
+        let ready = unsafe { mmap.atomic::<AtomicU32>(256).unwrap() };
+
+        let (guard, timed_out) = condvar
+            .wait_timeout(mutex.lock().unwrap(), Duration::from_millis(10))
+            .unwrap();
+        assert!(timed_out);
+        drop(guard);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let _guard = mutex.lock().unwrap();
+                ready.store(1, Ordering::SeqCst);
+                condvar.notify_all();
+            });
+            let mut guard = mutex.lock().unwrap();
+            while ready.load(Ordering::SeqCst) == 0 {
+                guard = condvar.wait(guard).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn semaphore() {
+        let mmap = MmapOptions::new().len(4096).map_anon().unwrap();
+        let semaphore = unsafe { Semaphore::init(&mmap, 0, 1).unwrap() };
+        assert_eq!(1, semaphore.value());
+
+        semaphore.wait();
+        assert!(!semaphore.try_wait());
+        assert!(!semaphore.wait_timeout(Duration::from_millis(10)));
+
+        thread::scope(|scope| {
+            scope.spawn(|| semaphore.post().unwrap());
+            semaphore.wait();
+        });
+        semaphore.post().unwrap();
+        assert!(semaphore.try_wait());
+        assert_eq!(0, semaphore.value());
+    }
+}
@@ -1,9 +1,19 @@
-extern crate libc;
-
-use std::fs::File;
+#[cfg(not(target_os = "android"))]
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::ops::Range;
+use std::os::unix::fs::MetadataExt;
+#[cfg(not(target_os = "android"))]
+use std::os::unix::io::FromRawFd;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{io, ptr};
 
+use libc;
+use protect::ProtectionMap;
+use {BackingState, HugePageSize, Protection};
+
 #[cfg(any(
     all(target_os = "linux", not(target_arch = "mips")),
     target_os = "freebsd",
@@ -18,21 +28,52 @@ const MAP_STACK: libc::c_int = libc::MAP_STACK;
 )))]
 const MAP_STACK: libc::c_int = 0;
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_POPULATE: libc::c_int = libc::MAP_POPULATE;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_POPULATE: libc::c_int = 0;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_LOCKED: libc::c_int = libc::MAP_LOCKED;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_LOCKED: libc::c_int = 0;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_NORESERVE: libc::c_int = libc::MAP_NORESERVE;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_NORESERVE: libc::c_int = 0;
+
 pub struct MmapInner {
     ptr: *mut libc::c_void,
     len: usize,
+    /// The offset of the memory map in the backing file.
+    #[cfg(target_os = "linux")]
+    offset: u64,
+    /// An advisory lock on the backing file.
+    lock: Option<FileLock>,
+    /// The state of the backing file when it was mapped.
+    backing: Option<Backing>,
+    /// The protection of the pages of the memory map.
+    protection: ProtectionMap,
 }
 
 impl MmapInner {
     /// Creates a new `MmapInner`.
     ///
-    /// This is a thin wrapper around the `mmap` sytem call.
+    /// This is a thin wrapper around the `mmap` sytem call. `addr` is a placement hint, or the
+    /// required placement with `MAP_FIXED`; it is null to let the kernel choose the placement.
+    /// `locked` adds `MAP_LOCKED` to the flags, where it is supported.
     fn new(
+        addr: *mut libc::c_void,
         len: usize,
         prot: libc::c_int,
         flags: libc::c_int,
         file: RawFd,
         offset: u64,
+        locked: bool,
     ) -> io::Result<MmapInner> {
         let alignment = offset % page_size() as u64;
         let aligned_offset = offset - alignment;
@@ -45,9 +86,10 @@ impl MmapInner {
             ));
         }
 
+        let flags = if locked { flags | MAP_LOCKED } else { flags };
         unsafe {
             let ptr = libc::mmap(
-                ptr::null_mut(),
+                addr,
                 aligned_len as libc::size_t,
                 prot,
                 flags,
@@ -56,66 +98,277 @@ impl MmapInner {
             );
 
             if ptr == libc::MAP_FAILED {
-                Err(io::Error::last_os_error())
+                let error = io::Error::last_os_error();
+                if locked {
+                    Err(memlock_error(error))
+                } else {
+                    Err(error)
+                }
             } else {
                 Ok(MmapInner {
-                    ptr: ptr.offset(alignment as isize),
-                    len: len,
+                    ptr: ptr.add(alignment as usize),
+                    len,
+                    #[cfg(target_os = "linux")]
+                    offset,
+                    lock: None,
+                    backing: None,
+                    protection: ProtectionMap::new(from_prot(prot)),
                 })
             }
         }
     }
 
-    pub fn map(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map(
+        len: usize,
+        file: &File,
+        offset: u64,
+        populate: bool,
+        locked: bool,
+    ) -> io::Result<MmapInner> {
+        let populate = if populate { MAP_POPULATE } else { 0 };
         MmapInner::new(
+            ptr::null_mut(),
             len,
             libc::PROT_READ,
-            libc::MAP_SHARED,
+            libc::MAP_SHARED | populate,
             file.as_raw_fd(),
             offset,
+            locked,
         )
     }
 
-    pub fn map_exec(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_exec(
+        len: usize,
+        file: &File,
+        offset: u64,
+        populate: bool,
+        locked: bool,
+    ) -> io::Result<MmapInner> {
+        let populate = if populate { MAP_POPULATE } else { 0 };
         MmapInner::new(
+            ptr::null_mut(),
             len,
             libc::PROT_READ | libc::PROT_EXEC,
-            libc::MAP_SHARED,
+            libc::MAP_SHARED | populate,
             file.as_raw_fd(),
             offset,
+            locked,
         )
     }
 
-    pub fn map_mut(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_mut(
+        len: usize,
+        file: &File,
+        offset: u64,
+        populate: bool,
+        locked: bool,
+    ) -> io::Result<MmapInner> {
+        let populate = if populate { MAP_POPULATE } else { 0 };
         MmapInner::new(
+            ptr::null_mut(),
             len,
             libc::PROT_READ | libc::PROT_WRITE,
-            libc::MAP_SHARED,
+            libc::MAP_SHARED | populate,
             file.as_raw_fd(),
             offset,
+            locked,
         )
     }
 
-    pub fn map_copy(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_copy(
+        len: usize,
+        file: &File,
+        offset: u64,
+        populate: bool,
+        locked: bool,
+    ) -> io::Result<MmapInner> {
+        let populate = if populate { MAP_POPULATE } else { 0 };
         MmapInner::new(
+            ptr::null_mut(),
             len,
             libc::PROT_READ | libc::PROT_WRITE,
-            libc::MAP_PRIVATE,
+            libc::MAP_PRIVATE | populate,
+            file.as_raw_fd(),
+            offset,
+            locked,
+        )
+    }
+
+    /// Maps a sealed file private and read-only.
+    #[cfg(target_os = "linux")]
+    pub fn map_sealed(
+        len: usize,
+        file: &File,
+        offset: u64,
+        populate: bool,
+        locked: bool,
+    ) -> io::Result<MmapInner> {
+        let populate = if populate { MAP_POPULATE } else { 0 };
+        MmapInner::new(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE | populate,
             file.as_raw_fd(),
             offset,
+            locked,
         )
     }
 
     /// Open an anonymous memory map.
-    pub fn map_anon(len: usize, stack: bool) -> io::Result<MmapInner> {
+    pub fn map_anon(
+        len: usize,
+        stack: bool,
+        populate: bool,
+        locked: bool,
+        huge: Option<HugePageSize>,
+        private: bool,
+    ) -> io::Result<MmapInner> {
+        let sharing = if private {
+            libc::MAP_PRIVATE
+        } else {
+            libc::MAP_SHARED
+        };
         let stack = if stack { MAP_STACK } else { 0 };
+        let populate = if populate { MAP_POPULATE } else { 0 };
+        let (len, huge_flags, huge_page_size) = match huge {
+            Some(size) => {
+                let page_size = huge_page_size(size)?;
+                let len = round_up(len, page_size)?;
+                (len, huge_page_flags(size), page_size)
+            }
+            None => (len, 0, 0),
+        };
         MmapInner::new(
+            ptr::null_mut(),
             len,
             libc::PROT_READ | libc::PROT_WRITE,
-            libc::MAP_SHARED | libc::MAP_ANON | stack,
+            sharing | libc::MAP_ANON | stack | populate | huge_flags,
             -1,
             0,
+            locked,
         )
+        .map_err(|error| match huge {
+            Some(_) => hugetlb_error(error, huge_page_size),
+            None => error,
+        })
+    }
+
+    /// Open a shared memory map backed by a new anonymous memory file.
+    ///
+    /// Returns the memory map, and the memory file created with `memfd_create`.
+    #[cfg(target_os = "linux")]
+    pub fn map_memfd(
+        name: &str,
+        len: usize,
+        populate: bool,
+        locked: bool,
+        huge: Option<HugePageSize>,
+        cloexec: bool,
+    ) -> io::Result<(MmapInner, File)> {
+        let name = CString::new(name).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "memfd name must not contain a nul byte",
+            )
+        })?;
+        let (len, huge_flags, huge_page_size) = match huge {
+            Some(size) => {
+                let page_size = huge_page_size(size)?;
+                // The MFD_HUGE_* size selectors share their encoding with MAP_HUGE_*.
+                let flags = libc::MFD_HUGETLB
+                    | (huge_page_flags(size) & !libc::MAP_HUGETLB) as libc::c_uint;
+                (round_up(len, page_size)?, flags, page_size)
+            }
+            None => (len, 0, 0),
+        };
+        let cloexec = if cloexec { libc::MFD_CLOEXEC } else { 0 };
+
+        let file = unsafe {
+            let fd = libc::memfd_create(
+                name.as_ptr(),
+                cloexec | libc::MFD_ALLOW_SEALING | huge_flags,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_fd(fd)
+        };
+        file.set_len(len as u64)?;
+
+        let inner =
+            MmapInner::map_mut(len, &file, 0, populate, locked).map_err(|error| match huge {
+                Some(_) => hugetlb_error(error, huge_page_size),
+                None => error,
+            })?;
+        Ok((inner, file))
+    }
+
+    /// Reserve an inaccessible range of address space, without committing memory to it.
+    pub fn reserve(len: usize) -> io::Result<MmapInner> {
+        MmapInner::new(
+            ptr::null_mut(),
+            len,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANON | MAP_NORESERVE,
+            -1,
+            0,
+            false,
+        )
+    }
+
+    /// Releases the memory of a range of a reservation, and makes it inaccessible.
+    pub fn decommit(&mut self, offset: usize, len: usize) -> io::Result<()> {
+        self.protect(offset, len, Protection::None)?;
+        self.madvise(libc::MADV_DONTNEED, offset, len)
+    }
+
+    /// Open a memory map of a new anonymous memory file, mapped twice back-to-back.
+    ///
+    /// The length is rounded up to a multiple of the page size. The returned memory map is twice
+    /// the length, and its second half mirrors its first.
+    #[cfg(target_os = "linux")]
+    pub fn map_ring(len: usize) -> io::Result<MmapInner> {
+        let len = round_up(len, page_size())?;
+        let reserved_len = len.checked_mul(2).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "ring buffer length overflows")
+        })?;
+
+        let file = unsafe {
+            let name = b"memmap-ring\0";
+            let fd = libc::memfd_create(name.as_ptr() as *const libc::c_char, libc::MFD_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_fd(fd)
+        };
+        file.set_len(len as u64)?;
+
+        // Reserve the address range for both halves, then map the memory file over each half.
+        let mut inner = MmapInner::new(
+            ptr::null_mut(),
+            reserved_len,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANON | MAP_NORESERVE,
+            -1,
+            0,
+            false,
+        )?;
+        for half in 0..2 {
+            let mirror = MmapInner::new(
+                unsafe { inner.ptr.add(half * len) },
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_FIXED,
+                file.as_raw_fd(),
+                0,
+                false,
+            )?;
+            // The halves are unmapped along with the reservation.
+            std::mem::forget(mirror);
+        }
+        inner.protection = ProtectionMap::new(Protection::ReadWrite);
+        Ok(inner)
     }
 
     pub fn flush(&self, offset: usize, len: usize) -> io::Result<()> {
@@ -137,7 +390,7 @@ impl MmapInner {
         let aligned_len = len + alignment;
         let result = unsafe {
             libc::msync(
-                self.ptr.offset(aligned_offset as isize),
+                self.ptr.add(aligned_offset),
                 aligned_len as libc::size_t,
                 libc::MS_ASYNC,
             )
@@ -149,29 +402,281 @@ impl MmapInner {
         }
     }
 
-    fn mprotect(&mut self, prot: libc::c_int) -> io::Result<()> {
+    /// Gives the kernel advice about the expected use of a range of the memory map.
+    ///
+    /// This is a thin wrapper around the `madvise` system call.
+    pub fn madvise(&self, advice: libc::c_int, offset: usize, len: usize) -> io::Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "madvise range is out of the bounds of the memory map",
+            ));
+        }
+        let alignment = (self.ptr as usize + offset) % page_size();
+        let offset = offset as isize - alignment as isize;
+        let len = len + alignment;
+        let result = unsafe { libc::madvise(self.ptr.offset(offset), len as libc::size_t, advice) };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Prefaults the pages of the memory map, for writing if `write` is set.
+    ///
+    /// Unlike `MAP_POPULATE`, failures to populate the pages are reported as errors. Kernels
+    /// older than Linux 5.14 reject `MADV_POPULATE_READ` with `EINVAL`; the memory map is then
+    /// only populated by `MAP_POPULATE`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn populate(&self, write: bool) -> io::Result<()> {
+        if write {
+            return self.madvise(libc::MADV_POPULATE_WRITE, 0, self.len);
+        }
+        match self.madvise(libc::MADV_POPULATE_READ, 0, self.len) {
+            Err(ref error) if error.raw_os_error() == Some(libc::EINVAL) => Ok(()),
+            result => result,
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn populate(&self, write: bool) -> io::Result<()> {
+        if write {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "write-populating memory maps is not supported on this platform",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Locks a range of the memory map into RAM with `mlock`.
+    ///
+    /// If `on_fault` is set, pages are locked as they are faulted in (`MLOCK_ONFAULT`), rather
+    /// than all being faulted in up front.
+    pub fn mlock(&self, offset: usize, len: usize, on_fault: bool) -> io::Result<()> {
+        let (ptr, len) = self.page_range(offset, len, "mlock")?;
+        let result = if on_fault {
+            mlock_on_fault(ptr, len)?
+        } else {
+            unsafe { libc::mlock(ptr, len as libc::size_t) }
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(memlock_error(io::Error::last_os_error()))
+        }
+    }
+
+    /// Unlocks a range of the memory map with `munlock`.
+    pub fn munlock(&self, offset: usize, len: usize) -> io::Result<()> {
+        let (ptr, len) = self.page_range(offset, len, "munlock")?;
+        if unsafe { libc::munlock(ptr, len as libc::size_t) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Returns whether each page overlapping a range of the memory map is resident in memory,
+    /// with `mincore`.
+    pub fn mincore(&self, offset: usize, len: usize) -> io::Result<Vec<bool>> {
+        let (ptr, len) = self.page_range(offset, len, "mincore")?;
+        let mut vec = vec![0; (len + page_size() - 1) / page_size()];
+        let result = unsafe {
+            libc::mincore(
+                ptr as *mut libc::c_void,
+                len as libc::size_t,
+                vec.as_mut_ptr() as *mut _,
+            )
+        };
+        if result == 0 {
+            // Only the least significant bit is defined; the others are reserved.
+            Ok(vec.into_iter().map(|page: u8| page & 1 != 0).collect())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Returns the number of bytes of the memory map which are resident in memory.
+    pub fn resident_bytes(&self) -> io::Result<usize> {
+        let page_size = page_size();
+        let alignment = self.ptr as usize % page_size;
+        let end = alignment + self.len;
+        let bytes = self
+            .mincore(0, self.len)?
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, resident)| resident)
+            .map(|(page, _)| {
+                let start = (page * page_size).max(alignment);
+                ((page + 1) * page_size).min(end) - start
+            })
+            .sum();
+        Ok(bytes)
+    }
+
+    /// Bounds checks a range of the memory map, and extends it down to a page boundary.
+    fn page_range(
+        &self,
+        offset: usize,
+        len: usize,
+        op: &str,
+    ) -> io::Result<(*const libc::c_void, usize)> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} range is out of the bounds of the memory map", op),
+            ));
+        }
+        let alignment = (self.ptr as usize + offset) % page_size();
+        let ptr = unsafe { self.ptr.add(offset).sub(alignment) };
+        Ok((ptr as *const libc::c_void, len + alignment))
+    }
+
+    /// Resizes the memory map with the `mremap` system call.
+    #[cfg(target_os = "linux")]
+    pub fn remap(&mut self, new_len: usize, may_move: bool) -> io::Result<()> {
+        if new_len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "memory map must have a non-zero length",
+            ));
+        }
+        let alignment = self.ptr as usize % page_size();
+        let flags = if may_move { libc::MREMAP_MAYMOVE } else { 0 };
         unsafe {
-            let alignment = self.ptr as usize % page_size();
-            let ptr = self.ptr.offset(-(alignment as isize));
-            let len = self.len + alignment;
-            if libc::mprotect(ptr, len, prot) == 0 {
-                Ok(())
-            } else {
+            let ptr = libc::mremap(
+                self.ptr.offset(-(alignment as isize)),
+                self.len + alignment,
+                new_len + alignment,
+                flags,
+            );
+            if ptr == libc::MAP_FAILED {
                 Err(io::Error::last_os_error())
+            } else {
+                self.ptr = ptr.add(alignment);
+                self.len = new_len;
+                self.protection.truncate(new_len);
+                Ok(())
             }
         }
     }
 
+    /// Takes an advisory `flock` lock on the file.
+    ///
+    /// The lock is taken through a new open file description of the file, so that it is
+    /// independent of other locks taken through the file, and releasing it does not release
+    /// them.
+    pub fn lock(file: &File, exclusive: bool, blocking: bool) -> io::Result<FileLock> {
+        let file = reopen(file)?;
+        let mut operation = if exclusive {
+            libc::LOCK_EX
+        } else {
+            libc::LOCK_SH
+        };
+        if !blocking {
+            operation |= libc::LOCK_NB;
+        }
+        while unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        Ok(FileLock { file })
+    }
+
+    /// Keeps the lock held for the lifetime of the memory map.
+    pub fn set_lock(&mut self, lock: FileLock) {
+        self.lock = Some(lock);
+    }
+
+    /// Records the state of the backing file, retaining a duplicate of its descriptor, and the
+    /// path it was opened through.
+    pub fn set_backing(&mut self, file: &File) -> io::Result<()> {
+        let metadata = file.metadata()?;
+        self.backing = Some(Backing {
+            path: backing_path(file, &metadata)?,
+            file: file.try_clone()?,
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            len: metadata.len(),
+            mtime: (metadata.mtime(), metadata.mtime_nsec()),
+        });
+        Ok(())
+    }
+
+    /// Compares the current state of the backing file with its state when it was mapped.
+    pub fn check_backing(&self) -> io::Result<BackingState> {
+        let backing = self.backing.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "memory map is not backed by a file",
+            )
+        })?;
+        if let Some(ref path) = backing.path {
+            match fs::metadata(path) {
+                Ok(ref metadata)
+                    if metadata.dev() == backing.dev && metadata.ino() == backing.ino => {}
+                Ok(_) => return Ok(BackingState::Replaced),
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                    return Ok(BackingState::Replaced)
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        let metadata = backing.file.metadata()?;
+        Ok(if metadata.len() < backing.len {
+            BackingState::Truncated
+        } else if metadata.len() > backing.len {
+            BackingState::Grown
+        } else if (metadata.mtime(), metadata.mtime_nsec()) != backing.mtime {
+            BackingState::Modified
+        } else {
+            BackingState::Unchanged
+        })
+    }
+
+    /// Returns the offset of the memory map in the backing file.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Changes the protection of a range of the memory map with `mprotect`.
+    ///
+    /// The ends of the range must be page boundaries, or the ends of the memory map.
+    pub fn protect(&mut self, offset: usize, len: usize, protection: Protection) -> io::Result<()> {
+        let alignment = (self.ptr as usize + offset) % page_size();
+        unsafe {
+            let ptr = self.ptr.add(offset).sub(alignment);
+            if libc::mprotect(ptr, len + alignment, prot(protection)) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        self.protection
+            .set(offset, offset + len, self.len, protection);
+        Ok(())
+    }
+
+    /// Returns the protection of the memory map, as ranges of equal protection.
+    pub fn protection(&self) -> Vec<(Range<usize>, Protection)> {
+        self.protection.runs(self.len)
+    }
+
     pub fn make_read_only(&mut self) -> io::Result<()> {
-        self.mprotect(libc::PROT_READ)
+        self.protect(0, self.len, Protection::Read)
     }
 
     pub fn make_exec(&mut self) -> io::Result<()> {
-        self.mprotect(libc::PROT_READ | libc::PROT_EXEC)
+        self.protect(0, self.len, Protection::ReadExec)
     }
 
     pub fn make_mut(&mut self) -> io::Result<()> {
-        self.mprotect(libc::PROT_READ | libc::PROT_WRITE)
+        self.protect(0, self.len, Protection::ReadWrite)
     }
 
     #[inline]
@@ -206,9 +711,437 @@ impl Drop for MmapInner {
     }
 }
 
+/// The state of the file backing a memory map.
+struct Backing {
+    /// A duplicate of the file's descriptor, through which its current state is read.
+    file: File,
+    dev: u64,
+    ino: u64,
+    len: u64,
+    mtime: (i64, i64),
+    /// The path the file was opened through, or `None` if the file has no path.
+    path: Option<PathBuf>,
+}
+
+/// An advisory lock on a file, released when dropped.
+pub struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // The open file description is private to the lock, but release the lock explicitly in
+        // case its descriptor was inherited by a child process.
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
 unsafe impl Sync for MmapInner {}
 unsafe impl Send for MmapInner {}
 
-fn page_size() -> usize {
-    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+/// Returns the path the file was opened through, through `/proc/self/fd`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn file_path(file: &File) -> io::Result<PathBuf> {
+    fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd()))
+}
+
+/// Returns the path the file was opened through, with `F_GETPATH`.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn file_path(file: &File) -> io::Result<PathBuf> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut path = vec![0u8; libc::PATH_MAX as usize];
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETPATH, path.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = path
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(path.len());
+    Ok(PathBuf::from(OsStr::from_bytes(&path[..len])))
+}
+
+/// Returns the path the file was opened through, or `None` if the file has been unlinked, as
+/// temporary files and memory files are.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+))]
+fn backing_path(file: &File, metadata: &fs::Metadata) -> io::Result<Option<PathBuf>> {
+    if metadata.nlink() == 0 {
+        return Ok(None);
+    }
+    file_path(file).map(Some).map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("failed to resolve the path of the file: {}", error),
+        )
+    })
+}
+
+/// The path of a file can not be resolved on this platform, so replaced files are not detected.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+fn backing_path(_file: &File, _metadata: &fs::Metadata) -> io::Result<Option<PathBuf>> {
+    Ok(None)
+}
+
+/// Opens a new open file description of the file, through `/proc/self/fd`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn reopen(file: &File) -> io::Result<File> {
+    File::open(format!("/proc/self/fd/{}", file.as_raw_fd())).map_err(reopen_error)
+}
+
+/// Opens a new open file description of the file, through its path.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn reopen(file: &File) -> io::Result<File> {
+    let path = file_path(file).map_err(reopen_error)?;
+    let reopened = File::open(path).map_err(reopen_error)?;
+    // The path may have been replaced by another file since the file was opened.
+    let (metadata, reopened_metadata) = (file.metadata()?, reopened.metadata()?);
+    if (metadata.dev(), metadata.ino()) != (reopened_metadata.dev(), reopened_metadata.ino()) {
+        return Err(reopen_error(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the path of the file refers to another file",
+        )));
+    }
+    Ok(reopened)
+}
+
+/// Locks belong to the open file description they are taken through, which can not be
+/// reopened here, so taking a lock through a duplicate descriptor would share it with the file.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+fn reopen(_file: &File) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "locking memory maps is not supported on this platform, since the file can not be \
+         reopened to isolate the lock",
+    ))
+}
+
+/// Explains a failure to reopen a file to lock it.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+))]
+fn reopen_error(error: io::Error) -> io::Error {
+    io::Error::new(
+        error.kind(),
+        format!("failed to reopen the file to lock it: {}", error),
+    )
+}
+
+/// Returns the size of a page, which is cached after the first call.
+pub fn page_size() -> usize {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+    match PAGE_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize };
+            PAGE_SIZE.store(page_size, Ordering::Relaxed);
+            page_size
+        }
+        page_size => page_size,
+    }
+}
+
+/// Rounds `len` up to a multiple of `page_size`.
+fn round_up(len: usize, page_size: usize) -> io::Result<usize> {
+    len.checked_add(page_size - 1)
+        .map(|len| len / page_size * page_size)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "memory map length overflows"))
+}
+
+/// Returns the granularity of memory map offsets, which is the page size.
+pub fn allocation_granularity() -> usize {
+    page_size()
+}
+
+/// Returns the sizes of the huge pages supported by the kernel, in ascending order.
+#[cfg(target_os = "linux")]
+pub fn huge_page_sizes() -> io::Result<Vec<usize>> {
+    let entries = match fs::read_dir("/sys/kernel/mm/hugepages") {
+        Ok(entries) => entries,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut sizes = Vec::new();
+    for entry in entries {
+        // Entries are named after the size of their huge pages, as in `hugepages-2048kB`.
+        let name = entry?.file_name();
+        let kb = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("hugepages-"))
+            .and_then(|name| name.strip_suffix("kB"))
+            .and_then(|kb| kb.parse::<usize>().ok());
+        if let Some(kb) = kb {
+            sizes.push(kb * 1024);
+        }
+    }
+    sizes.sort_unstable();
+    Ok(sizes)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn huge_page_sizes() -> io::Result<Vec<usize>> {
+    Ok(Vec::new())
+}
+
+/// Sets the length of the file, allocating disk space for it.
+///
+/// Allocating the space up front ensures that writes through a memory map of the file do not
+/// fail (with `SIGBUS`) when the file system is full.
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    unsafe {
+        let result = libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t);
+        if result == 0 {
+            return Ok(());
+        } else if result != libc::EOPNOTSUPP && result != libc::EINVAL {
+            return Err(io::Error::from_raw_os_error(result));
+        }
+        // The file system does not support allocation; fall back to extending the file.
+    }
+    file.set_len(len)
+}
+
+/// Opens a POSIX shared memory object with `shm_open`.
+#[cfg(not(target_os = "android"))]
+pub fn shm_open(name: &str, flags: libc::c_int, mode: u32) -> io::Result<File> {
+    let name = shm_name(name)?;
+    unsafe {
+        // POSIX requires shm_open to set FD_CLOEXEC on the new file descriptor.
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        let fd = libc::shm_open(name.as_ptr(), flags, mode as libc::mode_t);
+        // On Apple platforms, shm_open is variadic, so the mode is promoted to an unsigned int.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let fd = libc::shm_open(name.as_ptr(), flags, mode as libc::c_uint);
+        if fd < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(File::from_raw_fd(fd))
+        }
+    }
+}
+
+/// Removes the name of a POSIX shared memory object with `shm_unlink`.
+#[cfg(not(target_os = "android"))]
+pub fn shm_unlink(name: &str) -> io::Result<()> {
+    let name = shm_name(name)?;
+    if unsafe { libc::shm_unlink(name.as_ptr()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+fn shm_name(name: &str) -> io::Result<CString> {
+    CString::new(name).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "shared memory name must not contain a nul byte",
+        )
+    })
+}
+
+/// The seals which guarantee that the contents and length of a memory file can not change.
+#[cfg(target_os = "linux")]
+const IMMUTABLE_SEALS: libc::c_int = libc::F_SEAL_WRITE | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW;
+
+/// Seals the memory file against writes, shrinking and growing.
+#[cfg(target_os = "linux")]
+pub fn seal(file: &File) -> io::Result<()> {
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, IMMUTABLE_SEALS) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Returns an error unless the memory file is sealed against writes, shrinking and growing.
+#[cfg(target_os = "linux")]
+pub fn check_sealed(file: &File) -> io::Result<()> {
+    let seals = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GET_SEALS) };
+    if seals < 0 {
+        Err(io::Error::last_os_error())
+    } else if seals & IMMUTABLE_SEALS != IMMUTABLE_SEALS {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "file is not sealed with F_SEAL_WRITE, F_SEAL_SHRINK and F_SEAL_GROW",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn mlock_on_fault(ptr: *const libc::c_void, len: usize) -> io::Result<libc::c_int> {
+    // Called through `syscall`, since older C libraries do not wrap `mlock2`.
+    const MLOCK_ONFAULT: libc::c_uint = 1;
+    Ok(
+        unsafe { libc::syscall(libc::SYS_mlock2, ptr, len as libc::size_t, MLOCK_ONFAULT) }
+            as libc::c_int,
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mlock_on_fault(_ptr: *const libc::c_void, _len: usize) -> io::Result<libc::c_int> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "locking memory on fault is not supported on this platform",
+    ))
+}
+
+/// Returns the `mmap` protection flags of a protection.
+fn prot(protection: Protection) -> libc::c_int {
+    match protection {
+        Protection::None => libc::PROT_NONE,
+        Protection::Read => libc::PROT_READ,
+        Protection::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        Protection::ReadExec => libc::PROT_READ | libc::PROT_EXEC,
+        Protection::ReadWriteExec => libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+    }
+}
+
+/// Returns the protection of `mmap` protection flags.
+fn from_prot(prot: libc::c_int) -> Protection {
+    let write = prot & libc::PROT_WRITE != 0;
+    let exec = prot & libc::PROT_EXEC != 0;
+    match (prot & libc::PROT_READ != 0, write, exec) {
+        (false, false, false) => Protection::None,
+        (_, false, false) => Protection::Read,
+        (_, true, false) => Protection::ReadWrite,
+        (_, false, true) => Protection::ReadExec,
+        (_, true, true) => Protection::ReadWriteExec,
+    }
+}
+
+/// Locks all current and, if `future` is set, future mappings of the process with `mlockall`.
+pub fn mlockall(future: bool) -> io::Result<()> {
+    let flags = if future {
+        libc::MCL_CURRENT | libc::MCL_FUTURE
+    } else {
+        libc::MCL_CURRENT
+    };
+    if unsafe { libc::mlockall(flags) } == 0 {
+        Ok(())
+    } else {
+        Err(memlock_error(io::Error::last_os_error()))
+    }
+}
+
+/// Unlocks all mappings of the process with `munlockall`.
+pub fn munlockall() -> io::Result<()> {
+    if unsafe { libc::munlockall() } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Explains a failure to lock memory which is due to the `RLIMIT_MEMLOCK` resource limit.
+fn memlock_error(error: io::Error) -> io::Error {
+    match error.raw_os_error() {
+        Some(libc::ENOMEM) | Some(libc::EPERM) | Some(libc::EAGAIN) => {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+                return error;
+            }
+            let show = |value: libc::rlim_t| {
+                if value == libc::RLIM_INFINITY {
+                    "unlimited".to_string()
+                } else {
+                    format!("{} KiB", value / 1024)
+                }
+            };
+            io::Error::new(
+                error.kind(),
+                format!(
+                    "unable to lock memory ({}); the RLIMIT_MEMLOCK limit is {} (hard limit {}), \
+                     raise it with `ulimit -l` or grant CAP_IPC_LOCK",
+                    error,
+                    show(limit.rlim_cur),
+                    show(limit.rlim_max)
+                ),
+            )
+        }
+        _ => error,
+    }
+}
+
+/// Explains a failure to allocate memory from the hugetlb pool.
+fn hugetlb_error(error: io::Error, huge_page_size: usize) -> io::Error {
+    match error.raw_os_error() {
+        Some(libc::ENOMEM) => io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "no free {} KiB huge pages in the hugetlb pool; \
+                 reserve pages through /proc/sys/vm/nr_hugepages",
+                huge_page_size / 1024
+            ),
+        ),
+        _ => error,
+    }
+}
+
+/// Returns the size in bytes of the huge pages of the given size class.
+#[cfg(target_os = "linux")]
+fn huge_page_size(size: HugePageSize) -> io::Result<usize> {
+    match size {
+        HugePageSize::Default => {
+            let meminfo = ::std::fs::read_to_string("/proc/meminfo")?;
+            meminfo
+                .lines()
+                .find(|line| line.starts_with("Hugepagesize:"))
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|kb| kb.parse::<usize>().ok())
+                .map(|kb| kb * 1024)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "huge pages are not supported"))
+        }
+        HugePageSize::Size2MiB => Ok(2 << 20),
+        HugePageSize::Size1GiB => Ok(1 << 30),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn huge_page_size(_size: HugePageSize) -> io::Result<usize> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "huge pages are not supported on this platform",
+    ))
+}
+
+/// Returns the `mmap` flags selecting huge pages of the given size class.
+#[cfg(target_os = "linux")]
+fn huge_page_flags(size: HugePageSize) -> libc::c_int {
+    libc::MAP_HUGETLB
+        | match size {
+            HugePageSize::Default => 0,
+            HugePageSize::Size2MiB => libc::MAP_HUGE_2MB,
+            HugePageSize::Size1GiB => libc::MAP_HUGE_1GB,
+        }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn huge_page_flags(_size: HugePageSize) -> libc::c_int {
+    0
 }
@@ -0,0 +1,316 @@
+use std::sync::atomic;
+use std::{error, fmt, io, mem, slice};
+
+/// Types which are valid for any bit pattern, and so can be viewed from arbitrary bytes.
+///
+/// This is implemented for the primitive integer and floating point types, and for arrays of
+/// types which implement it.
+///
+/// # Safety
+///
+/// Implementing types must be inhabited by every bit pattern of their size: they must not
+/// contain references, `bool`s, `char`s, enums, or other types with invalid bit patterns. They
+/// must not contain interior mutability.
+pub unsafe trait FromBytes: Sized {}
+
+/// Types which can be viewed as bytes.
+///
+/// This is implemented for the primitive integer and floating point types, and for arrays of
+/// types which implement it. Mutable views require both `FromBytes` and `AsBytes`, since values
+/// written through a view are read back as bytes.
+///
+/// # Safety
+///
+/// Implementing types must not contain padding or other uninitialized bytes.
+pub unsafe trait AsBytes {}
+
+macro_rules! impl_bytes {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl FromBytes for $ty {}
+            unsafe impl AsBytes for $ty {}
+        )*
+    };
+}
+
+impl_bytes!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: FromBytes, const N: usize> FromBytes for [T; N] {}
+unsafe impl<T: AsBytes, const N: usize> AsBytes for [T; N] {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Atomic integer types which can be viewed from the bytes of a memory map.
+///
+/// This trait is sealed. It is implemented for the atomic integer types of `std::sync::atomic`
+/// which the target supports natively. Native atomics are lock-free, which is required for them
+/// to be coherent when the memory map is shared between processes.
+pub trait Atomic: private::Sealed + Sync {}
+
+macro_rules! impl_atomic {
+    ($($width:tt: $($ty:ident),*;)*) => {
+        $($(
+            #[cfg(target_has_atomic = $width)]
+            impl private::Sealed for atomic::$ty {}
+            #[cfg(target_has_atomic = $width)]
+            impl Atomic for atomic::$ty {}
+        )*)*
+    };
+}
+
+impl_atomic! {
+    "8": AtomicU8, AtomicI8;
+    "16": AtomicU16, AtomicI16;
+    "32": AtomicU32, AtomicI32;
+    "64": AtomicU64, AtomicI64;
+    "ptr": AtomicUsize, AtomicIsize;
+}
+
+/// The error returned when a range of a memory map can not be viewed as a slice of a type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewError {
+    /// The range is out of the bounds of the memory map.
+    OutOfBounds {
+        /// The offset of the range.
+        offset: usize,
+        /// The length of the range.
+        len: usize,
+        /// The length of the memory map.
+        map_len: usize,
+    },
+    /// The start of the range is not aligned for the type.
+    Misaligned {
+        /// The offset of the range.
+        offset: usize,
+        /// The alignment of the type.
+        align: usize,
+    },
+    /// The length of the range is not a multiple of the size of the type.
+    Length {
+        /// The length of the range.
+        len: usize,
+        /// The size of the type.
+        size: usize,
+    },
+    /// The type is zero-sized, so the number of elements in the range is undefined.
+    ZeroSized,
+    /// The memory map is read-only, so the range can not be written to.
+    ReadOnly,
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViewError::OutOfBounds {
+                offset,
+                len,
+                map_len,
+            } => write!(
+                fmt,
+                "range of {} bytes at offset {} is out of the bounds of the memory map of {} bytes",
+                len, offset, map_len
+            ),
+            ViewError::Misaligned { offset, align } => write!(
+                fmt,
+                "offset {} is not aligned to the type alignment of {} bytes",
+                offset, align
+            ),
+            ViewError::Length { len, size } => write!(
+                fmt,
+                "length {} is not a multiple of the type size of {} bytes",
+                len, size
+            ),
+            ViewError::ZeroSized => write!(fmt, "zero-sized types can not be viewed"),
+            ViewError::ReadOnly => write!(fmt, "the memory map is read-only"),
+        }
+    }
+}
+
+impl error::Error for ViewError {}
+
+impl From<ViewError> for io::Error {
+    fn from(error: ViewError) -> io::Error {
+        let kind = match error {
+            ViewError::ReadOnly => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// Checks that the range of the memory map can be viewed as a slice of `T`, and returns the
+/// number of elements.
+pub fn check<T>(
+    ptr: *const u8,
+    map_len: usize,
+    offset: usize,
+    len: usize,
+) -> Result<usize, ViewError> {
+    if offset.checked_add(len).map_or(true, |end| end > map_len) {
+        return Err(ViewError::OutOfBounds {
+            offset,
+            len,
+            map_len,
+        });
+    }
+    let size = mem::size_of::<T>();
+    if size == 0 {
+        return Err(ViewError::ZeroSized);
+    }
+    let align = mem::align_of::<T>();
+    if (ptr as usize + offset) % align != 0 {
+        return Err(ViewError::Misaligned { offset, align });
+    }
+    if len % size != 0 {
+        return Err(ViewError::Length { len, size });
+    }
+    Ok(len / size)
+}
+
+/// Views a range of the bytes as a slice of `T`.
+pub fn view<T: FromBytes>(bytes: &[u8], offset: usize, len: usize) -> Result<&[T], ViewError> {
+    let count = check::<T>(bytes.as_ptr(), bytes.len(), offset, len)?;
+    unsafe {
+        Ok(slice::from_raw_parts(
+            bytes.as_ptr().add(offset) as *const T,
+            count,
+        ))
+    }
+}
+
+/// Views a range of the bytes as a mutable slice of `T`.
+pub fn view_mut<T: FromBytes + AsBytes>(
+    bytes: &mut [u8],
+    offset: usize,
+    len: usize,
+) -> Result<&mut [T], ViewError> {
+    let count = check::<T>(bytes.as_ptr(), bytes.len(), offset, len)?;
+    unsafe {
+        Ok(slice::from_raw_parts_mut(
+            bytes.as_mut_ptr().add(offset) as *mut T,
+            count,
+        ))
+    }
+}
+
+/// Views a range of the memory map at `ptr` as a slice of atomics.
+///
+/// The caller must ensure that `ptr` is valid for writes of `map_len` bytes, and that the range
+/// is only accessed atomically for the lifetime `'a`.
+pub unsafe fn view_atomic<'a, A: Atomic>(
+    ptr: *mut u8,
+    map_len: usize,
+    offset: usize,
+    len: usize,
+) -> Result<&'a [A], ViewError> {
+    let count = check::<A>(ptr, map_len, offset, len)?;
+    Ok(slice::from_raw_parts(ptr.add(offset) as *const A, count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::ViewError;
+    use MmapOptions;
+
+    #[test]
+    fn view() {
+        let mut mmap = MmapOptions::new().len(64).map_anon().unwrap();
+        mmap[..8].copy_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0]);
+
+        let ints = mmap.view::<u32>().unwrap();
+        assert_eq!(16, ints.len());
+        assert_eq!(u32::from_le(1), ints[0]);
+        assert_eq!(u32::from_le(2), ints[1]);
+
+        let mmap = mmap.make_read_only().unwrap();
+        let pairs = mmap.view_range::<[u32; 2]>(8, 16).unwrap();
+        assert_eq!(&[[0, 0], [0, 0]], pairs);
+        assert_eq!(&[0u8; 3], mmap.view_range::<u8>(61, 3).unwrap());
+    }
+
+    #[test]
+    fn view_mut() {
+        let mut mmap = MmapOptions::new().len(64).map_anon().unwrap();
+        mmap.view_range_mut::<u64>(8, 16).unwrap()[1] = u64::MAX;
+        assert_eq!(&[0xff; 8], &mmap[16..24]);
+        assert_eq!(&[0; 16], &mmap[..16]);
+
+        mmap.view_mut::<f64>().unwrap()[0] = 1.5;
+        assert_eq!(1.5, mmap.view::<f64>().unwrap()[0]);
+    }
+
+    #[test]
+    #[cfg(target_has_atomic = "64")]
+    fn atomic() {
+        use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+        let mut mmap = MmapOptions::new().len(64).map_anon().unwrap();
+        mmap[8] = 1;
+
+        let counters = unsafe { mmap.atomic_slice::<AtomicU64>(8, 16).unwrap() };
+        assert_eq!(2, counters.len());
+        assert_eq!(u64::from_le(1), counters[0].load(Ordering::SeqCst));
+        counters[1].fetch_add(5, Ordering::SeqCst);
+
+        let counter = unsafe { mmap.atomic::<AtomicU32>(60).unwrap() };
+        counter.store(7, Ordering::SeqCst);
+        assert_eq!(7, counter.load(Ordering::SeqCst));
+        assert_eq!(
+            5,
+            unsafe { mmap.atomic::<AtomicU64>(16) }
+                .unwrap()
+                .load(Ordering::SeqCst)
+        );
+
+        assert_eq!(
+            Err(ViewError::Misaligned {
+                offset: 4,
+                align: 8
+            }),
+            unsafe { mmap.atomic::<AtomicU64>(4) }.map(|_| ())
+        );
+        assert_eq!(
+            Err(ViewError::OutOfBounds {
+                offset: 62,
+                len: 4,
+                map_len: 64
+            }),
+            unsafe { mmap.atomic::<AtomicU32>(62) }.map(|_| ())
+        );
+    }
+
+    #[test]
+    fn view_errors() {
+        let mmap = MmapOptions::new().len(64).map_anon().unwrap();
+        assert_eq!(
+            Err(ViewError::Misaligned {
+                offset: 2,
+                align: 4
+            }),
+            mmap.view_range::<u32>(2, 4)
+        );
+        assert_eq!(
+            Err(ViewError::Length { len: 6, size: 4 }),
+            mmap.view_range::<u32>(0, 6)
+        );
+        assert_eq!(
+            Err(ViewError::OutOfBounds {
+                offset: 60,
+                len: 8,
+                map_len: 64
+            }),
+            mmap.view_range::<u32>(60, 8)
+        );
+        assert_eq!(
+            Err(ViewError::OutOfBounds {
+                offset: usize::MAX,
+                len: 1,
+                map_len: 64
+            }),
+            mmap.view_range::<u8>(usize::MAX, 1)
+        );
+        assert_eq!(Err(ViewError::ZeroSized), mmap.view::<[u32; 0]>());
+    }
+}
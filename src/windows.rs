@@ -1,19 +1,30 @@
 use std::fs::File;
+use std::ops::Range;
 use std::os::raw::c_void;
-use std::os::windows::io::{AsRawHandle, RawHandle};
+use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 use std::{io, mem, ptr};
 
+use protect::ProtectionMap;
+use {BackingState, HugePageSize, Protection};
+
 use winapi::shared::basetsd::SIZE_T;
 use winapi::shared::minwindef::DWORD;
+use winapi::um::fileapi::{LockFileEx, UnlockFile};
 use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
 use winapi::um::memoryapi::{
-    CreateFileMappingW, FlushViewOfFile, MapViewOfFile, UnmapViewOfFile, VirtualProtect,
-    FILE_MAP_ALL_ACCESS, FILE_MAP_COPY, FILE_MAP_EXECUTE, FILE_MAP_READ, FILE_MAP_WRITE,
+    CreateFileMappingW, FlushViewOfFile, GetLargePageMinimum, MapViewOfFile, UnmapViewOfFile,
+    VirtualProtect, FILE_MAP_ALL_ACCESS, FILE_MAP_COPY, FILE_MAP_EXECUTE, FILE_MAP_READ,
+    FILE_MAP_WRITE,
 };
-use winapi::um::sysinfoapi::GetSystemInfo;
+use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY, OVERLAPPED};
+use winapi::um::sysinfoapi::{GetSystemInfo, SYSTEM_INFO};
+use winapi::um::winbase::ReOpenFile;
 use winapi::um::winnt::{
-    PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_READONLY,
-    PAGE_READWRITE, PAGE_WRITECOPY,
+    FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, PAGE_EXECUTE_READ,
+    PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE,
+    PAGE_WRITECOPY,
 };
 
 pub struct MmapInner {
@@ -21,6 +32,12 @@ pub struct MmapInner {
     ptr: *mut c_void,
     len: usize,
     copy: bool,
+    /// An advisory lock on the backing file.
+    lock: Option<FileLock>,
+    /// The state of the backing file when it was mapped.
+    backing: Option<Backing>,
+    /// The protection of the pages of the memory map.
+    protection: ProtectionMap,
 }
 
 impl MmapInner {
@@ -66,15 +83,24 @@ impl MmapInner {
             } else {
                 Ok(MmapInner {
                     file: Some(file.try_clone()?),
-                    ptr: ptr.offset(alignment as isize),
+                    ptr: ptr.add(alignment as usize),
                     len: len as usize,
-                    copy: copy,
+                    copy,
+                    lock: None,
+                    backing: None,
+                    protection: ProtectionMap::new(from_page_protection(protect)),
                 })
             }
         }
     }
 
-    pub fn map(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map(
+        len: usize,
+        file: &File,
+        offset: u64,
+        _populate: bool,
+        _locked: bool,
+    ) -> io::Result<MmapInner> {
         let write = protection_supported(file.as_raw_handle(), PAGE_READWRITE);
         let exec = protection_supported(file.as_raw_handle(), PAGE_EXECUTE_READ);
         let mut access = FILE_MAP_READ;
@@ -101,7 +127,13 @@ impl MmapInner {
         Ok(inner)
     }
 
-    pub fn map_exec(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_exec(
+        len: usize,
+        file: &File,
+        offset: u64,
+        _populate: bool,
+        _locked: bool,
+    ) -> io::Result<MmapInner> {
         let write = protection_supported(file.as_raw_handle(), PAGE_READWRITE);
         let mut access = FILE_MAP_READ | FILE_MAP_EXECUTE;
         let protection = if write {
@@ -118,7 +150,13 @@ impl MmapInner {
         Ok(inner)
     }
 
-    pub fn map_mut(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_mut(
+        len: usize,
+        file: &File,
+        offset: u64,
+        _populate: bool,
+        _locked: bool,
+    ) -> io::Result<MmapInner> {
         let exec = protection_supported(file.as_raw_handle(), PAGE_EXECUTE_READ);
         let mut access = FILE_MAP_READ | FILE_MAP_WRITE;
         let protection = if exec {
@@ -135,7 +173,13 @@ impl MmapInner {
         Ok(inner)
     }
 
-    pub fn map_copy(len: usize, file: &File, offset: u64) -> io::Result<MmapInner> {
+    pub fn map_copy(
+        len: usize,
+        file: &File,
+        offset: u64,
+        _populate: bool,
+        _locked: bool,
+    ) -> io::Result<MmapInner> {
         let exec = protection_supported(file.as_raw_handle(), PAGE_EXECUTE_READWRITE);
         let mut access = FILE_MAP_COPY;
         let protection = if exec {
@@ -152,7 +196,21 @@ impl MmapInner {
         Ok(inner)
     }
 
-    pub fn map_anon(len: usize, _stack: bool) -> io::Result<MmapInner> {
+    pub fn map_anon(
+        len: usize,
+        _stack: bool,
+        _populate: bool,
+        _locked: bool,
+        huge: Option<HugePageSize>,
+        _private: bool,
+    ) -> io::Result<MmapInner> {
+        if huge.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "huge pages are not supported on this platform",
+            ));
+        }
+
         unsafe {
             // Create a mapping and view with maximum access permissions, then use `VirtualProtect`
             // to set the actual `Protection`. This way, we can set more permissive protection later
@@ -183,9 +241,12 @@ impl MmapInner {
             if result != 0 {
                 Ok(MmapInner {
                     file: None,
-                    ptr: ptr,
+                    ptr,
                     len: len as usize,
                     copy: false,
+                    lock: None,
+                    backing: None,
+                    protection: ProtectionMap::new(Protection::ReadWrite),
                 })
             } else {
                 Err(io::Error::last_os_error())
@@ -202,7 +263,7 @@ impl MmapInner {
     }
 
     pub fn flush_async(&self, offset: usize, len: usize) -> io::Result<()> {
-        let result = unsafe { FlushViewOfFile(self.ptr.offset(offset as isize), len as SIZE_T) };
+        let result = unsafe { FlushViewOfFile(self.ptr.add(offset), len as SIZE_T) };
         if result != 0 {
             Ok(())
         } else {
@@ -210,41 +271,173 @@ impl MmapInner {
         }
     }
 
-    fn virtual_protect(&mut self, protect: DWORD) -> io::Result<()> {
+    pub fn reserve(_len: usize) -> io::Result<MmapInner> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "address space reservations are not supported on this platform",
+        ))
+    }
+
+    pub fn decommit(&mut self, _offset: usize, _len: usize) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "address space reservations are not supported on this platform",
+        ))
+    }
+
+    pub fn populate(&self, write: bool) -> io::Result<()> {
+        if write {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "write-populating memory maps is not supported on this platform",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn mlock(&self, _offset: usize, _len: usize, _on_fault: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "locking memory maps is not supported on this platform",
+        ))
+    }
+
+    pub fn munlock(&self, _offset: usize, _len: usize) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "locking memory maps is not supported on this platform",
+        ))
+    }
+
+    pub fn mincore(&self, _offset: usize, _len: usize) -> io::Result<Vec<bool>> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "page residency queries are not supported on this platform",
+        ))
+    }
+
+    pub fn resident_bytes(&self) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "page residency queries are not supported on this platform",
+        ))
+    }
+
+    /// Takes an advisory `LockFileEx` lock on the whole file.
+    ///
+    /// The lock is taken through a reopened handle of the file, so that it is independent of
+    /// other locks taken through the file, and releasing it does not release them.
+    pub fn lock(file: &File, exclusive: bool, blocking: bool) -> io::Result<FileLock> {
+        let file = unsafe {
+            let handle = ReOpenFile(
+                file.as_raw_handle(),
+                GENERIC_READ,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                0,
+            );
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            File::from_raw_handle(handle as RawHandle)
+        };
+        let mut flags = 0;
+        if exclusive {
+            flags |= LOCKFILE_EXCLUSIVE_LOCK;
+        }
+        if !blocking {
+            flags |= LOCKFILE_FAIL_IMMEDIATELY;
+        }
         unsafe {
-            let alignment = self.ptr as usize % allocation_granularity();
-            let ptr = self.ptr.offset(-(alignment as isize));
-            let aligned_len = self.len as SIZE_T + alignment as SIZE_T;
+            let mut overlapped: OVERLAPPED = mem::zeroed();
+            let result = LockFileEx(file.as_raw_handle(), flags, 0, !0, !0, &mut overlapped);
+            if result == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(FileLock { file })
+    }
 
-            let mut old = 0;
-            let result = VirtualProtect(ptr, aligned_len, protect, &mut old);
+    /// Records the state of the backing file.
+    pub fn set_backing(&mut self, file: &File) -> io::Result<()> {
+        let metadata = file.metadata()?;
+        self.backing = Some(Backing {
+            len: metadata.len(),
+            modified: metadata.modified()?,
+        });
+        Ok(())
+    }
 
-            if result != 0 {
-                Ok(())
-            } else {
-                Err(io::Error::last_os_error())
+    /// Compares the current state of the backing file with its state when it was mapped.
+    ///
+    /// Replaced files are not detected.
+    pub fn check_backing(&self) -> io::Result<BackingState> {
+        let (file, backing) = match (self.file.as_ref(), self.backing.as_ref()) {
+            (Some(file), Some(backing)) => (file, backing),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "memory map is not backed by a file",
+                ))
+            }
+        };
+        let metadata = file.metadata()?;
+        Ok(if metadata.len() < backing.len {
+            BackingState::Truncated
+        } else if metadata.len() > backing.len {
+            BackingState::Grown
+        } else if metadata.modified()? != backing.modified {
+            BackingState::Modified
+        } else {
+            BackingState::Unchanged
+        })
+    }
+
+    /// Keeps the lock held for the lifetime of the memory map.
+    pub fn set_lock(&mut self, lock: FileLock) {
+        self.lock = Some(lock);
+    }
+
+    /// Changes the protection of a range of the memory map with `VirtualProtect`.
+    ///
+    /// The ends of the range must be page boundaries, or the ends of the memory map.
+    pub fn protect(&mut self, offset: usize, len: usize, protection: Protection) -> io::Result<()> {
+        unsafe {
+            let alignment = (self.ptr as usize + offset) % page_size();
+            let ptr = (self.ptr as *mut u8).add(offset).sub(alignment) as *mut c_void;
+            let aligned_len = len as SIZE_T + alignment as SIZE_T;
+            let protect = page_protection(protection, self.copy);
+
+            let mut old = 0;
+            if VirtualProtect(ptr, aligned_len, protect, &mut old) == 0 {
+                return Err(io::Error::last_os_error());
             }
         }
+        self.protection
+            .set(offset, offset + len, self.len, protection);
+        Ok(())
+    }
+
+    /// Returns the protection of the memory map, as ranges of equal protection.
+    pub fn protection(&self) -> Vec<(Range<usize>, Protection)> {
+        self.protection.runs(self.len)
     }
 
     pub fn make_read_only(&mut self) -> io::Result<()> {
-        self.virtual_protect(PAGE_READONLY)
+        self.protect(0, self.len, Protection::Read)
     }
 
     pub fn make_exec(&mut self) -> io::Result<()> {
+        // Copy-on-write memory maps stay writeable, so that written pages remain private.
         if self.copy {
-            self.virtual_protect(PAGE_EXECUTE_WRITECOPY)
+            self.protect(0, self.len, Protection::ReadWriteExec)
         } else {
-            self.virtual_protect(PAGE_EXECUTE_READ)
+            self.protect(0, self.len, Protection::ReadExec)
         }
     }
 
     pub fn make_mut(&mut self) -> io::Result<()> {
-        if self.copy {
-            self.virtual_protect(PAGE_WRITECOPY)
-        } else {
-            self.virtual_protect(PAGE_READWRITE)
-        }
+        self.protect(0, self.len, Protection::ReadWrite)
     }
 
     #[inline]
@@ -277,9 +470,57 @@ impl Drop for MmapInner {
     }
 }
 
+/// The state of the file backing a memory map.
+struct Backing {
+    len: u64,
+    modified: SystemTime,
+}
+
+/// An advisory lock on a file, released when dropped.
+pub struct FileLock {
+    file: File,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        unsafe {
+            UnlockFile(self.file.as_raw_handle(), 0, 0, !0, !0);
+        }
+    }
+}
+
 unsafe impl Sync for MmapInner {}
 unsafe impl Send for MmapInner {}
 
+/// Sets the length of the file.
+pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    file.set_len(len)
+}
+
+/// Returns the page protection constant of a protection.
+fn page_protection(protection: Protection, copy: bool) -> DWORD {
+    match (protection, copy) {
+        (Protection::None, _) => PAGE_NOACCESS,
+        (Protection::Read, _) => PAGE_READONLY,
+        (Protection::ReadWrite, false) => PAGE_READWRITE,
+        (Protection::ReadWrite, true) => PAGE_WRITECOPY,
+        (Protection::ReadExec, _) => PAGE_EXECUTE_READ,
+        (Protection::ReadWriteExec, false) => PAGE_EXECUTE_READWRITE,
+        (Protection::ReadWriteExec, true) => PAGE_EXECUTE_WRITECOPY,
+    }
+}
+
+/// Returns the protection of a page protection constant.
+fn from_page_protection(protect: DWORD) -> Protection {
+    match protect {
+        PAGE_READONLY => Protection::Read,
+        PAGE_READWRITE | PAGE_WRITECOPY => Protection::ReadWrite,
+        PAGE_EXECUTE_READ => Protection::ReadExec,
+        PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY => Protection::ReadWriteExec,
+        _ => Protection::None,
+    }
+}
+
 fn protection_supported(handle: RawHandle, protection: DWORD) -> bool {
     unsafe {
         let handle = CreateFileMappingW(handle, ptr::null_mut(), protection, 0, 0, ptr::null());
@@ -291,10 +532,39 @@ fn protection_supported(handle: RawHandle, protection: DWORD) -> bool {
     }
 }
 
-fn allocation_granularity() -> usize {
-    unsafe {
-        let mut info = mem::zeroed();
-        GetSystemInfo(&mut info);
-        return info.dwAllocationGranularity as usize;
+/// Returns the page size and the allocation granularity, which are cached after the first call.
+fn system_info() -> (usize, usize) {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+    static ALLOCATION_GRANULARITY: AtomicUsize = AtomicUsize::new(0);
+    // The allocation granularity is stored first, so it is set once the page size is.
+    match PAGE_SIZE.load(Ordering::Acquire) {
+        0 => {
+            let mut info: SYSTEM_INFO = unsafe { mem::zeroed() };
+            unsafe { GetSystemInfo(&mut info) };
+            let page_size = info.dwPageSize as usize;
+            let allocation_granularity = info.dwAllocationGranularity as usize;
+            ALLOCATION_GRANULARITY.store(allocation_granularity, Ordering::Release);
+            PAGE_SIZE.store(page_size, Ordering::Release);
+            (page_size, allocation_granularity)
+        }
+        page_size => (page_size, ALLOCATION_GRANULARITY.load(Ordering::Acquire)),
+    }
+}
+
+/// Returns the size of a page.
+pub fn page_size() -> usize {
+    system_info().0
+}
+
+/// Returns the granularity of memory map offsets.
+pub fn allocation_granularity() -> usize {
+    system_info().1
+}
+
+/// Returns the size of large pages, if they are supported.
+pub fn huge_page_sizes() -> io::Result<Vec<usize>> {
+    match unsafe { GetLargePageMinimum() } {
+        0 => Ok(Vec::new()),
+        size => Ok(vec![size]),
     }
 }